@@ -1,10 +1,23 @@
-use pw;
+use std::f64::consts::PI;
 
-use na::{Point3, Point4, Matrix4, PerspectiveMatrix3, ToHomogeneous, FromHomogeneous};
+use na::{self, Point3, Vector3, Norm};
 
+use pose::Pose;
+use projection::Projection;
+use depth_cue::DepthCue;
+use draw_list::{DrawList, Primitive};
 use util;
 use consts::*;
 
+// How arrows are drawn
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ArrowStyle {
+    // Flat lines with a fixed size arrowhead
+    Lines,
+    // Solid shafts and cones, shaded by a light over the camera's shoulder
+    Glyphs,
+}
+
 pub struct Arrow {
     pub tail: Point3<f64>,
     pub head: Point3<f64>,
@@ -15,48 +28,88 @@ pub struct Arrow {
 }
 
 impl Arrow {
-    pub fn map_transform(&mut self, mat: &Matrix4<f64>) {
-        self.tail = transform_in_homo(self.tail, mat);
-        self.head = transform_in_homo(self.head, mat);
+    pub fn map_transform(&mut self, pose: &Pose) {
+        self.tail = pose.transform_point(&self.tail);
+        self.head = pose.transform_point(&self.head);
     }
 
-    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d, persp: &PerspectiveMatrix3<f64>, camera: &Matrix4<f64>, view: [f64; 4]) {
-        if let Some(path) = self.project_to_viewport(persp, camera, view) {
-            let line_style =
-                pw::Line::new(self.color(), 1.0);
-            line_style.draw_arrow(path, 5.0, &c.draw_state, c.transform, gl);
+    // Add the projection of the arrow to `list`
+    pub fn draw(&self, list: &mut DrawList, style: ArrowStyle, projection: &Projection, camera: &Pose, cue: &DepthCue, view: [f64; 4]) {
+        match style {
+            ArrowStyle::Lines => self.draw_lines(list, projection, camera, cue, view),
+            ArrowStyle::Glyphs => self.draw_glyph(list, projection, camera, cue, view),
         }
     }
 
-    fn project_to_viewport(
-        &self,
-        persp: &PerspectiveMatrix3<f64>,
-        camera: &Matrix4<f64>,
-        viewport: [f64; 4]
-    )
-    -> Option<[f64; 4]> {
+    // A line, at the depth of its midpoint. Only the part of the line in
+    // view is drawn, and the arrowhead only if the head is in view.
+    fn draw_lines(&self, list: &mut DrawList, projection: &Projection, camera: &Pose, cue: &DepthCue, view: [f64; 4]) {
         // Transform relative to the camera position:
-        let headr: Point3<f64> = transform_in_homo(self.head, camera);
-        let tailr: Point3<f64> = transform_in_homo(self.tail, camera);
-        if headr.z <= NEAR_PLANE_Z || tailr.z <= NEAR_PLANE_Z {
-            None
-        } else {
-            // Project onto "device" surface:
-            let head_prime = persp.project_point(&headr);
-            let tail_prime = persp.project_point(&tailr);
-            // Trasform to viewport surface:
-            let scale_factor = 0.3 * util::f64_min(viewport[2], viewport[3]);
-            let cx = viewport[0] + viewport[2] * 0.5;
-            let cy = viewport[1] + viewport[3] * 0.5;
-            Some([
-                tail_prime.x * scale_factor + cx, // x0
-                tail_prime.y * scale_factor + cy, // y0
-                head_prime.x * scale_factor + cx, // x1
-                head_prime.y * scale_factor + cy, // y1
-            ])
+        let tailr = camera.transform_point(&self.tail);
+        let headr = camera.transform_point(&self.head);
+        if let Some((t0, t1)) = projection.clip_segment(&tailr, &headr, view) {
+            let d = headr - tailr;
+            let (from, to) = (tailr + d * t0, tailr + d * t1);
+            // Project onto viewport surface:
+            let from_prime = projection.to_viewport(&from, view);
+            let to_prime = projection.to_viewport(&to, view);
+            let depth = (from.z + to.z) * 0.5;
+            list.push(depth, Primitive::Arrow {
+                line: [from_prime[0], from_prime[1], to_prime[0], to_prime[1]],
+                head: if t1 < 1.0 { 0.0 } else { 5.0 },
+                radius: cue.line_radius(1.0, depth),
+                color: cue.color(self.color(), depth),
+            });
+        }
+    }
+
+    // A cylindrical shaft capped by a cone, as a polygon per face facing
+    // the camera. Each face is drawn at the depth of its centroid, so that
+    // sorting the draw list also orders the faces of overlapping glyphs.
+    fn draw_glyph(&self, list: &mut DrawList, projection: &Projection, camera: &Pose, cue: &DepthCue, view: [f64; 4]) {
+        let tail = camera.transform_point(&self.tail);
+        let head = camera.transform_point(&self.head);
+        let length = (head - tail).norm();
+        if length < 1e-9 {
+            return;
+        }
+        let dir = (head - tail) / length;
+        // Perpendicular axes across the arrow
+        let other = if dir.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+        let u = na::cross(&dir, &other).normalize();
+        let v = na::cross(&dir, &u);
+        let around = |i: usize| {
+            let angle = 2.0 * PI * i as f64 / GLYPH_SIDES as f64;
+            u * angle.cos() + v * angle.sin()
+        };
+
+        let base = head - dir * (length * GLYPH_HEAD_LENGTH);
+        let head_r = length * GLYPH_HEAD_RADIUS;
+        let shaft_r = length * GLYPH_SHAFT_RADIUS;
+        // The slope of the cone's sides tilts their normals towards the tip
+        let cone_tilt = head_r / (length * GLYPH_HEAD_LENGTH);
+        let base_color = self.glyph_color();
+        for i in 0..GLYPH_SIDES {
+            let (r0, r1) = (around(i), around(i + 1));
+            let mid = (r0 + r1).normalize();
+            // Side of the shaft
+            let shaft = [tail + r0 * shaft_r, tail + r1 * shaft_r, base + r1 * shaft_r, base + r0 * shaft_r];
+            add_face(list, &shaft, &mid, base_color, projection, cue, view);
+            // Side of the cone
+            let cone = [base + r0 * head_r, base + r1 * head_r, head];
+            add_face(list, &cone, &(mid + dir * cone_tilt).normalize(), base_color, projection, cue, view);
+            // Bottom of the cone
+            let bottom = [base + r1 * head_r, base + r0 * head_r, base];
+            add_face(list, &bottom, &-dir, base_color, projection, cue, view);
         }
     }
 
+    // Unshaded color of a glyph: a fixed hue with the opacity of the line
+    // drawn for the arrow
+    fn glyph_color(&self) -> [f32; 4] {
+        [GLYPH_COLOR[0], GLYPH_COLOR[1], GLYPH_COLOR[2], self.color()[3]]
+    }
+
     fn color(&self) -> [f32; 4] {
         // Calls to f##_max(..., 0.0) ensure slight imprecision will not
         // result in a negative channel value, which makes the color trip out
@@ -78,7 +131,83 @@ impl Arrow {
     }
 }
 
-// Lift a Point3 to Point4, apply a Matrix4, then flatten it back to Point3
-fn transform_in_homo(pt: Point3<f64>, mat: &Matrix4<f64>) -> Point3<f64> {
-    <Point3<f64> as FromHomogeneous<Point4<f64>>>::from(&(util::ref_mat4_mul(mat, pt.to_homogeneous())))
+
+// Add the face with corners `pts` (relative to the camera) and outward
+// `normal` to `list`, if it faces the camera, with Lambert shading. Only
+// the part in front of the near plane is drawn.
+fn add_face(list: &mut DrawList, pts: &[Point3<f64>], normal: &Vector3<f64>, color: [f32; 4],
+            projection: &Projection, cue: &DepthCue, view: [f64; 4]) {
+    let mut centroid = Vector3::new(0.0, 0.0, 0.0);
+    for p in pts {
+        centroid = centroid + p.to_vector();
+    }
+    centroid = centroid / pts.len() as f64;
+    // Seen from the camera at the origin, the face must point back at it
+    // (for an orthographic camera, back along the z axis)
+    let to_face = if projection.is_orthographic() { Vector3::new(0.0, 0.0, 1.0) } else { centroid };
+    if na::dot(normal, &to_face) >= 0.0 {
+        return;
+    }
+    let pts = match projection.clip_polygon(pts) {
+        Some(pts) => pts,
+        None => return,
+    };
+    let light = Vector3::new(GLYPH_LIGHT[0], GLYPH_LIGHT[1], GLYPH_LIGHT[2]).normalize();
+    let lambert = util::f64_max(na::dot(normal, &light), 0.0);
+    let shade = (GLYPH_AMBIENT + (1.0 - GLYPH_AMBIENT) * lambert) as f32;
+    let shaded = [color[0] * shade, color[1] * shade, color[2] * shade, color[3]];
+    list.push(centroid.z, Primitive::Polygon {
+        points: pts.iter().map(|p| projection.to_viewport(p, view)).collect(),
+        color: cue.color(shaded, centroid.z),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use na::Point3;
+
+    use pose::Pose;
+    use projection::Projection;
+    use depth_cue::DepthCue;
+    use draw_list::{DrawList, Primitive};
+
+    use super::*;
+
+    const VIEW: [f64; 4] = [0.0, 0.0, 400.0, 300.0];
+
+    // The sizes of the arrowheads of the lines drawn for an arrow from
+    // `tail` to `head`, relative to the camera
+    fn heads(tail: Point3<f64>, head: Point3<f64>) -> Vec<f64> {
+        let arrow = Arrow { tail: tail, head: head, field: 0.5, potential: 0.5 };
+        let mut list = DrawList::new();
+        arrow.draw(&mut list, ArrowStyle::Lines, &Projection::new(), &Pose::identity(), &DepthCue::new(), VIEW);
+        list.iter()
+            .map(|&(_, ref primitive)| match *primitive {
+                Primitive::Arrow { head, .. } => head,
+                _ => panic!("expected a line"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn head_drawn_when_in_view() {
+        let heads = heads(Point3::new(0.0, 0.0, 50.0), Point3::new(10.0, 0.0, 50.0));
+        assert_eq!(heads.len(), 1);
+        assert!(heads[0] > 0.0);
+    }
+
+    #[test]
+    fn head_omitted_when_tip_off_side() {
+        assert_eq!(heads(Point3::new(0.0, 0.0, 50.0), Point3::new(200.0, 0.0, 50.0)), vec![0.0]);
+    }
+
+    #[test]
+    fn head_omitted_when_tip_behind_camera() {
+        assert_eq!(heads(Point3::new(0.0, 0.0, 10.0), Point3::new(0.0, 0.0, -10.0)), vec![0.0]);
+    }
+
+    #[test]
+    fn hidden_arrow_not_drawn() {
+        assert!(heads(Point3::new(0.0, 0.0, -10.0), Point3::new(0.0, 5.0, -20.0)).is_empty());
+    }
 }
@@ -0,0 +1,100 @@
+use std::time::{Duration, Instant};
+
+use num::Zero;
+
+use na::Norm;
+
+use field::{FieldData, FieldView, VectorField};
+use point_charge::{BarnesHutField, plates, samples_between_plates};
+use units::Units;
+
+use consts::*;
+
+// Time serial and parallel rebuilds of the arrows of each view, and check
+// that both produce identical arrows. Run with `field-visualizer --bench`.
+pub fn run(views: Vec<(&str, &mut FieldView)>) {
+    println!("{:<24} {:>8} {:>12} {:>12} {:>8}", "scene", "arrows", "serial (ms)", "parallel (ms)", "speedup");
+    for (name, view) in views {
+        // Warm up (and start rayon's thread pool) before timing
+        view.populate_field();
+
+        let serial = time_rebuilds(|| view.populate_field_serial());
+        let serial_arrows: Vec<_> = view.arrows().iter().map(|a| (a.tail, a.head)).collect();
+
+        let parallel = time_rebuilds(|| view.populate_field());
+        let deterministic = view.arrows().iter()
+            .map(|a| (a.tail, a.head))
+            .eq(serial_arrows.into_iter());
+
+        println!("{:<24} {:>8} {:>12.3} {:>12.3} {:>7.2}x{}",
+                 name,
+                 view.arrows().len(),
+                 serial,
+                 parallel,
+                 serial / parallel,
+                 if deterministic { "" } else { "  (MISMATCH)" });
+    }
+
+    println!("");
+    for &n in &[10_000, 40_000] {
+        barnes_hut(n);
+    }
+}
+
+// Compare Barnes-Hut with direct summation on a pair of plates of `n`
+// charges in total, like a finely discretized capacitor. The error is
+// the greatest difference between the two field vectors at any sample
+// point, relative to the RMS field strength over all sample points.
+fn barnes_hut(n: usize) {
+    // At least `n` charges
+    let side = (n as f64 / 2.0).sqrt().ceil() as i64;
+    let charges = plates(side);
+    let samples = samples_between_plates(BENCH_SAMPLES, n as u64);
+
+    let start = Instant::now();
+    let direct: Vec<FieldData> = samples.iter()
+        .map(|p| charges.iter()
+            .map(|chg| chg.field_data_at(p, &Units::legacy()))
+            .fold(Zero::zero(), |f0: FieldData, f1| f0 + f1))
+        .collect();
+    let direct_ms = millis(start.elapsed());
+    let rms = (direct.iter()
+        .map(|f| f.force_vec.norm_squared())
+        .fold(0.0, |a, b| a + b) / direct.len() as f64).sqrt();
+
+    println!("Barnes-Hut, {} charges, {} samples: direct summation {:.1} ms",
+             charges.len(), samples.len(), direct_ms);
+    println!("{:>8} {:>10} {:>12} {:>12} {:>12} {:>10}", "theta", "nodes", "build (ms)", "eval (ms)", "rel. error", "bound");
+    for &theta in &[0.2, 0.5, 0.8] {
+        let start = Instant::now();
+        let tree = BarnesHutField::new(&charges, theta, Units::legacy());
+        let build_ms = millis(start.elapsed());
+
+        let start = Instant::now();
+        let approx: Vec<FieldData> = samples.iter().map(|p| tree.field_data_at(p)).collect();
+        let eval_ms = millis(start.elapsed());
+
+        let error = approx.iter().zip(direct.iter())
+            .map(|(a, d)| (a.force_vec - d.force_vec).norm())
+            .fold(0.0, |a: f64, b| a.max(b)) / rms;
+        // Monopole-only Barnes-Hut has error of order theta^2
+        let bound = theta * theta;
+        println!("{:>8.2} {:>10} {:>12.3} {:>12.3} {:>12.2e} {:>10.2e}{}",
+                 theta, tree.node_count(), build_ms, eval_ms, error, bound,
+                 if error <= bound { "" } else { "  (EXCEEDED)" });
+    }
+    println!("");
+}
+
+// Average duration in milliseconds of BENCH_ITERATIONS calls to `rebuild`
+fn time_rebuilds<F: FnMut()>(mut rebuild: F) -> f64 {
+    let start = Instant::now();
+    for _ in 0..BENCH_ITERATIONS {
+        rebuild();
+    }
+    millis(start.elapsed()) / BENCH_ITERATIONS as f64
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1_000_000.0
+}
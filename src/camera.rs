@@ -0,0 +1,232 @@
+use std::f64::consts::PI;
+
+use na::{Point3, Vector3, UnitQuaternion, Norm};
+
+use field::FieldView;
+use pose::{self, Pose};
+
+use util;
+use consts::*;
+
+// Mouse-driven control of a FieldView's camera: dragging orbits around a
+// target point, right-dragging pans, and scrolling zooms towards the
+// target. Every movement is applied through FieldView::transform_camera,
+// so it composes with the keyboard controls.
+pub struct OrbitControl {
+    // The point orbited around, in the coordinates of the arrows
+    pub target: Point3<f64>,
+
+    drag: Option<Drag>,
+    cursor: [f64; 2],
+
+    // Rotation (about the camera's x and y axes) applied per update once
+    // an orbiting drag is released; it decays by ORBIT_INERTIA each update
+    spin: (f64, f64),
+    // Rotation applied by dragging since the last update
+    dragged: (f64, f64),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Drag {
+    Orbit,
+    Pan,
+}
+
+impl OrbitControl {
+    pub fn new(target: Point3<f64>) -> OrbitControl {
+        OrbitControl {
+            target: target,
+            drag: None,
+            cursor: [0.0, 0.0],
+            spin: (0.0, 0.0),
+            dragged: (0.0, 0.0),
+        }
+    }
+
+    pub fn dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn start_drag(&mut self, drag: Drag) {
+        self.drag = Some(drag);
+        self.spin = (0.0, 0.0);
+        self.dragged = (0.0, 0.0);
+    }
+
+    pub fn end_drag(&mut self) {
+        if self.drag == Some(Drag::Orbit) {
+            // Keep spinning at the rate of the last update's motion
+            self.spin = self.dragged;
+        }
+        self.drag = None;
+    }
+
+    // Stop any motion and retarget, eg. after switching fields
+    pub fn retarget(&mut self, target: Point3<f64>) {
+        self.target = target;
+        self.drag = None;
+        self.spin = (0.0, 0.0);
+    }
+
+    // Handle a movement of the cursor to `pos` in `view`, returning whether
+    // the camera moved
+    pub fn cursor_moved(&mut self, field: &mut FieldView, pos: [f64; 2], view: [f64; 4]) -> bool {
+        let (dx, dy) = (pos[0] - self.cursor[0], pos[1] - self.cursor[1]);
+        self.cursor = pos;
+        match self.drag {
+            Some(Drag::Orbit) => {
+                let (pitch, yaw) = (-dy * ORBIT_PER_PIXEL, dx * ORBIT_PER_PIXEL);
+                self.orbit(field, pitch, yaw);
+                self.dragged.0 += pitch;
+                self.dragged.1 += yaw;
+                true
+            },
+            Some(Drag::Pan) => {
+                self.pan(field, dx, dy, view);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Continue spinning after an orbiting drag was released, returning
+    // whether the camera moved
+    pub fn update(&mut self, field: &mut FieldView) -> bool {
+        if self.dragging() {
+            self.dragged = (0.0, 0.0);
+            return false;
+        }
+        let (pitch, yaw) = self.spin;
+        if pitch.abs() + yaw.abs() < ORBIT_MIN_SPIN {
+            self.spin = (0.0, 0.0);
+            return false;
+        }
+        self.orbit(field, pitch, yaw);
+        self.spin = (pitch * ORBIT_INERTIA, yaw * ORBIT_INERTIA);
+        true
+    }
+
+    // Rotate the camera about the target
+    pub fn orbit(&self, field: &mut FieldView, pitch: f64, yaw: f64) {
+        let target = self.target_in_camera(field).to_vector();
+        field.transform_camera(
+            &Pose::translation(-target)
+                .then(&Pose::euler(pitch, yaw, 0.0))
+                .then(&Pose::translation(target)));
+    }
+
+    // Move the camera parallel to the view plane by (dx, dy) pixels,
+    // dragging the target along with the cursor
+    pub fn pan(&self, field: &mut FieldView, dx: f64, dy: f64, view: [f64; 4]) {
+        let depth = util::f64_max(self.target_in_camera(field).z, NEAR_PLANE_Z);
+        let scale = 1.0 / field.projection().pixels_per_unit(depth, view);
+        field.transform_camera(&Pose::translation(Vector3::new(dx * scale, dy * scale, 0.0)));
+    }
+
+    // Move the camera towards (positive `steps`) or away from the target.
+    // Moving an orthographic camera would not change the view, so that
+    // instead narrows or widens its view.
+    pub fn zoom(&self, field: &mut FieldView, steps: f64) {
+        if field.projection().is_orthographic() {
+            field.projection_mut().ortho_half_width *= ZOOM_PER_STEP.powf(-steps);
+            return;
+        }
+        let target = self.target_in_camera(field).to_vector();
+        // Keep the target beyond the near plane
+        let dist = target.norm();
+        // On the target there is no direction to zoom along
+        if dist < 1e-9 {
+            return;
+        }
+        let new_dist = util::f64_max(dist * ZOOM_PER_STEP.powf(-steps), NEAR_PLANE_Z * 2.0);
+        field.transform_camera(&Pose::translation(-target * (1.0 - new_dist / dist)));
+    }
+
+    // Place the camera, keeping its orientation, so that the sphere about
+    // `center` of the given `radius` fills the view, and orbit around it
+    pub fn frame(&mut self, field: &mut FieldView, center: Point3<f64>, radius: f64) {
+        let center_cam = field.camera().transform_point(&center);
+        let dist = util::f64_max(radius * FRAME_MARGIN / field.projection().half_fov_tan(), NEAR_PLANE_Z * 2.0);
+        if field.projection().is_orthographic() {
+            field.projection_mut().ortho_half_width = radius * FRAME_MARGIN;
+        }
+        field.transform_camera(&Pose::translation(Vector3::new(0.0, 0.0, dist) - center_cam.to_vector()));
+        self.retarget(center);
+    }
+
+    // Orient the camera exactly as `rotation`, keeping the target the same
+    // distance away, centered in the view
+    pub fn snap(&mut self, field: &mut FieldView, rotation: UnitQuaternion<f64>) {
+        let dist = self.target_in_camera(field).to_vector().norm();
+        let rotated_target = Pose::rotation(rotation).rotate(&self.target.to_vector());
+        field.set_camera(Pose {
+            rotation: rotation,
+            translation: Vector3::new(0.0, 0.0, dist) - rotated_target,
+        });
+        self.spin = (0.0, 0.0);
+    }
+
+    // Distance of the target in front of the camera
+    pub fn target_depth(&self, field: &FieldView) -> f64 {
+        self.target_in_camera(field).z
+    }
+
+    fn target_in_camera(&self, field: &FieldView) -> Point3<f64> {
+        field.camera().transform_point(&self.target)
+    }
+}
+
+// Views looking along the axes. Note that -y is up on the screen in the
+// front view.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AxisView {
+    Front, // looking along +z
+    Back, // along -z
+    Top, // along +y
+    Bottom, // along -y
+    Left, // along +x
+    Right, // along -x
+}
+
+impl AxisView {
+    // The rotation of a camera with this view
+    pub fn rotation(&self) -> UnitQuaternion<f64> {
+        let (x, y) = match *self {
+            AxisView::Front => (0.0, 0.0),
+            AxisView::Back => (0.0, PI),
+            AxisView::Top => (PI * 0.5, 0.0),
+            AxisView::Bottom => (-PI * 0.5, 0.0),
+            AxisView::Left => (0.0, -PI * 0.5),
+            AxisView::Right => (0.0, PI * 0.5),
+        };
+        pose::euler_quaternion(x, y, 0.0)
+    }
+}
+
+// Center of the grid of arrows of `field`, a natural point to orbit around
+pub fn grid_center(field: &FieldView) -> Point3<f64> {
+    let b = field.bounds();
+    let center = Point3::new((b[0].0 + b[0].1) * 0.5, (b[1].0 + b[1].1) * 0.5, (b[2].0 + b[2].1) * 0.5);
+    field.arrow_transforms().transform_point(&center)
+}
+
+// Center and radius of a sphere containing all of `points`
+pub fn bounding_sphere(points: &[Point3<f64>]) -> Option<(Point3<f64>, f64)> {
+    if points.is_empty() {
+        return None;
+    }
+    let (mut lo, mut hi) = (points[0], points[0]);
+    for p in points {
+        lo.x = util::f64_min(lo.x, p.x);
+        lo.y = util::f64_min(lo.y, p.y);
+        lo.z = util::f64_min(lo.z, p.z);
+        hi.x = util::f64_max(hi.x, p.x);
+        hi.y = util::f64_max(hi.y, p.y);
+        hi.z = util::f64_max(hi.z, p.z);
+    }
+    let center = Point3::new((lo.x + hi.x) * 0.5, (lo.y + hi.y) * 0.5, (lo.z + hi.z) * 0.5);
+    let radius = points.iter()
+        .map(|p| (*p - center).norm())
+        .fold(GRID_S, util::f64_max);
+    Some((center, radius))
+}
@@ -31,10 +31,185 @@ pub const POTENTIAL_SHADING: bool = true;
 pub const COLORFUL_POTENTIAL: bool = false;
 
 pub const NEAR_PLANE_Z: f64 = 1.0;
-pub const FAR_PLANE_Z: f64 = 100.0;
+// Arrows are clipped beyond this depth, well past the back of the default
+// grid (at a depth of 105) so that zooming out does not cut it off
+pub const FAR_PLANE_Z: f64 = 2000.0;
+
+// Projection:
+pub const DEFAULT_FOV_DEGREES: f64 = 88.8;
+pub const MIN_FOV_DEGREES: f64 = 20.0;
+pub const MAX_FOV_DEGREES: f64 = 120.0;
+pub const DEFAULT_ORTHO_HALF_WIDTH: f64 = 75.0;
 
 // Maximum and minimum lengths of a field vector:
 pub const FIELD_VEC_MAX_LEN: f64 = GRID_DIAG * 0.8;
 pub const FIELD_VEC_MIN_LEN: f64 = GRID_DIAG * 0.1;
 
 pub const FIELD_VEC_LEN_RANGE: f64 = FIELD_VEC_MAX_LEN - FIELD_VEC_MIN_LEN;
+
+// Charge dynamics:
+pub const DEFAULT_CHARGE_MASS: f64 = 1.0;
+pub const DYNAMICS_SOFTENING: f64 = GRID_S_2;
+pub const DYNAMICS_SUBSTEPS: u32 = 8;
+pub const DYNAMICS_BTN_W: f64 = 150.0;
+pub const DYNAMICS_BTN_H: f64 = 20.0;
+
+// Number of rebuilds timed per scene by `--bench`
+pub const BENCH_ITERATIONS: u32 = 20;
+
+// Fields of at least this many charges are evaluated with Barnes-Hut:
+pub const BARNES_HUT_MIN_CHARGES: usize = 2000;
+pub const BARNES_HUT_THETA: f64 = 0.5;
+// Number of points at which Barnes-Hut is compared with direct summation
+pub const BENCH_SAMPLES: usize = 500;
+
+// Number of incremental updates of a field after which its cached totals
+// are re-summed from the per-charge contributions
+pub const INCREMENTAL_RESYNC_INTERVAL: u32 = 64;
+// Fields whose per-charge contributions would take more memory than this
+// are rebuilt from scratch every time instead
+pub const CONTRIBUTION_CACHE_BYTES: usize = 256 * 1024 * 1024;
+
+// Limits on the number of arrows in a grid:
+pub const MAX_UNIFORM_ARROWS: usize = 20000;
+pub const DEFAULT_ARROW_BUDGET: usize = 1500;
+
+// Grid settings:
+pub const GRID_BTN_W: f64 = 150.0;
+pub const GRID_BTN_H: f64 = 20.0;
+pub const GRID_MIN_EXTENT: f64 = GRID_S;
+pub const GRID_MAX_EXTENT: f64 = 20.0 * GRID_S;
+pub const GRID_MIN_SPACING: f64 = GRID_S * 0.25;
+pub const GRID_MAX_SPACING: f64 = GRID_S * 2.0;
+pub const MIN_ARROW_BUDGET: usize = 100;
+pub const MAX_ARROW_BUDGET: usize = 5000;
+
+// Mouse camera controls:
+pub const ORBIT_PER_PIXEL: f64 = 0.005; // radians
+pub const ORBIT_INERTIA: f64 = 0.9; // fraction of spin kept each update
+pub const ORBIT_MIN_SPIN: f64 = 1e-4;
+pub const ZOOM_PER_STEP: f64 = 1.1;
+pub const FRAME_MARGIN: f64 = 1.2;
+pub const VIEW_BTN_W: f64 = 120.0;
+pub const VIEW_BTN_H: f64 = 20.0;
+pub const AXIS_BTN_W: f64 = 60.0;
+
+// Picking and dragging charges:
+pub const PICK_RADIUS: f64 = 12.0; // pixels
+pub const CHARGE_SNAP_STEP: f64 = GRID_S_2;
+
+// Charge editor:
+pub const EDITOR_ROWS: usize = 6;
+pub const EDITOR_SMALL_BTN_W: f64 = 25.0;
+pub const EDITOR_ACTION_BTN_W: f64 = 70.0;
+pub const EDITOR_TEXTBOX_H: f64 = 22.0;
+
+// Undo history:
+pub const HISTORY_LIMIT: usize = 200; // steps kept per field
+
+// Scenes:
+pub const DEFAULT_SCENE: &'static str = "Two opposite charges";
+// Limits on the scenes and per-scene controls, for which widget ids are
+// reserved in main.rs (checked when compiling)
+pub const MAX_SCENES: usize = 16;
+pub const MAX_SCENE_CONTROLS: usize = 8;
+
+// Charges drawn in the view:
+pub const POSITIVE_CHARGE_COLOR: [f32; 4] = [0.85, 0.15, 0.1, 0.9];
+pub const NEGATIVE_CHARGE_COLOR: [f32; 4] = [0.1, 0.25, 0.85, 0.9];
+pub const CHARGE_BORDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+pub const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.6, 0.0, 1.0];
+pub const CHARGE_RADIUS_PER_UNIT: f64 = 0.5;
+pub const MIN_CHARGE_RADIUS_PX: f64 = 2.0;
+pub const MAX_CHARGE_RADIUS_PX: f64 = 40.0;
+pub const CHARGE_LABEL_FONT_SIZE: u32 = 12;
+pub const MAX_LABELLED_CHARGES: usize = 20;
+
+// Depth cueing:
+// Depth at which depth-cued lines are drawn as without depth cueing (that
+// of the grid from the initial cameras)
+pub const DEPTH_CUE_REFERENCE: f64 = 75.0;
+pub const DEPTH_CUE_MIN_FACTOR: f64 = 0.25;
+pub const DEPTH_CUE_MAX_FACTOR: f64 = 3.0;
+pub const DEFAULT_FOG_START: f64 = 80.0;
+pub const DEFAULT_FOG_END: f64 = 250.0;
+pub const MAX_FOG_DISTANCE: f64 = 500.0;
+pub const DEPTH_BTN_W: f64 = 140.0;
+
+// 3D arrow glyphs, with sizes as fractions of the arrow's length:
+pub const GLYPH_SIDES: usize = 8;
+pub const GLYPH_HEAD_LENGTH: f64 = 0.35;
+pub const GLYPH_HEAD_RADIUS: f64 = 0.12;
+pub const GLYPH_SHAFT_RADIUS: f64 = 0.04;
+pub const GLYPH_COLOR: [f32; 4] = [0.3, 0.4, 0.75, 1.0];
+// Direction towards the light, relative to the camera (up and to the left)
+pub const GLYPH_LIGHT: [f64; 3] = [-0.4, -0.6, -0.7];
+// Brightness of faces turned away from the light
+pub const GLYPH_AMBIENT: f64 = 0.3;
+
+// Line integral convolution:
+pub const LIC_RESOLUTION: u32 = 256; // texels across the wider side of the slice
+pub const LIC_STEPS: usize = 20; // texels traced along the streamline each way
+pub const LIC_CONTRAST: f64 = 3.0;
+pub const LIC_NOISE_SEED: u64 = 7;
+pub const LIC_TILES: usize = 16; // tiles along each side when drawn in perspective
+
+// Flow particles:
+pub const PARTICLE_COUNT: usize = 2000;
+pub const MAX_PARTICLE_COUNT: usize = 10000;
+pub const PARTICLE_SPEED: f64 = 2.0; // grid spacings per second
+pub const MAX_PARTICLE_SPEED: f64 = 10.0;
+pub const PARTICLE_LIFETIME: f64 = 4.0; // seconds, on average
+pub const PARTICLE_TRAIL_LEN: usize = 12; // points kept in each trail
+pub const PARTICLE_RADIUS: f64 = 0.75;
+pub const PARTICLE_COLOR: [f32; 4] = [0.9, 0.45, 0.0, 0.9];
+pub const PARTICLE_SEED: u64 = 11;
+
+// Recording:
+pub const RECORD_WIDTH: u32 = 800;
+pub const RECORD_HEIGHT: u32 = 600;
+pub const RECORD_FPS: f64 = 25.0;
+// Seconds for which particles are advanced before the first frame
+pub const RECORD_PARTICLE_WARMUP: f64 = 1.0;
+// Samples along each side of a pixel when rendering without a window
+pub const RASTER_SUPERSAMPLE: usize = 3;
+
+// Camera keyframes:
+pub const KEYFRAME_INTERVAL: f64 = 2.0; // seconds between recorded keyframes
+pub const KEYFRAME_BTN_W: f64 = 90.0;
+
+// Parameter sweeps:
+pub const SWEEP_DURATION: f64 = 5.0; // seconds
+pub const MAX_SWEEP_DURATION: f64 = 30.0;
+pub const MAX_SWEEP_CHARGE: f64 = 20.0;
+
+// Probes:
+pub const MAX_PROBES: usize = 8;
+pub const PROBE_COLOR: [f32; 4] = [0.1, 0.6, 0.1, 1.0];
+pub const PROBE_MARKER_SIZE: f64 = 5.0; // pixels from the center to a corner
+
+// Force report:
+pub const FORCE_ARROW_LENGTH: f64 = GRID_S * 2.0; // of the arrow of the greatest force
+pub const FORCE_ARROW_HEAD: f64 = 8.0; // pixels
+pub const FORCE_ARROW_RADIUS: f64 = 1.5;
+pub const FORCE_ARROW_COLOR: [f32; 4] = [0.55, 0.1, 0.6, 1.0];
+// Charges beyond this many are left out of the sidebar's list of forces
+pub const MAX_FORCE_READOUTS: usize = 10;
+
+// Null points:
+pub const NULL_POINT_CELLS_PER_SPACING: f64 = 2.0;
+pub const NULL_POINT_MAX_ITERATIONS: usize = 30;
+// Newton iteration stops when a step is shorter than this, in cells
+pub const NULL_POINT_TOLERANCE: f64 = 1e-7;
+// Step of the central differences for the Jacobian, in cells
+pub const NULL_POINT_DIFF_STEP: f64 = 1e-3;
+// Eigenvalues smaller than this fraction of the largest are taken as zero
+pub const NULL_POINT_DEGENERACY: f64 = 1e-3;
+pub const NULL_POINT_MARKER_SIZE: f64 = 6.0; // radius in pixels
+// Colors of the kinds of null point, in the order of `NullKind`
+pub const NULL_POINT_COLORS: [[f32; 4]; 3] = [
+    [0.85, 0.5, 0.0, 1.0],
+    [0.0, 0.6, 0.6, 1.0],
+    [0.4, 0.4, 0.4, 1.0],
+];
+pub const MAX_NULL_READOUTS: usize = 8;
@@ -0,0 +1,63 @@
+use util;
+use consts::*;
+
+// How depth is conveyed in a view: drawing farther arrows first, making
+// nearer arrows thicker and more opaque, and fading far arrows into the
+// background
+pub struct DepthCue {
+    // Draw arrows back to front, rather than in the order of the grid
+    pub sort: bool,
+    // Scale the width of lines inversely with depth
+    pub scale_width: bool,
+    // Scale the opacity of lines inversely with depth
+    pub fade: bool,
+    // Blend lines towards the background between depths fog_start and
+    // fog_end, beyond which they vanish
+    pub fog: bool,
+    pub fog_start: f64,
+    pub fog_end: f64,
+}
+
+impl DepthCue {
+    pub fn new() -> DepthCue {
+        DepthCue {
+            sort: true,
+            scale_width: false,
+            fade: false,
+            fog: false,
+            fog_start: DEFAULT_FOG_START,
+            fog_end: DEFAULT_FOG_END,
+        }
+    }
+
+    // Factor by which lines at `depth` are thickened or made more opaque,
+    // relative to lines at DEPTH_CUE_REFERENCE
+    fn falloff(depth: f64) -> f64 {
+        let f = DEPTH_CUE_REFERENCE / util::f64_max(depth, NEAR_PLANE_Z);
+        util::f64_min(util::f64_max(f, DEPTH_CUE_MIN_FACTOR), DEPTH_CUE_MAX_FACTOR)
+    }
+
+    // Radius of a line which is `radius` thick without depth cueing
+    pub fn line_radius(&self, radius: f64, depth: f64) -> f64 {
+        if self.scale_width {
+            radius * DepthCue::falloff(depth)
+        } else {
+            radius
+        }
+    }
+
+    // Color of something at `depth` which is `color` without depth cueing
+    pub fn color(&self, color: [f32; 4], depth: f64) -> [f32; 4] {
+        let mut alpha = color[3] as f64;
+        if self.fade {
+            alpha = util::f64_min(alpha * DepthCue::falloff(depth), 1.0);
+        }
+        if self.fog {
+            // The background is white, so fading out is the same as
+            // blending towards white
+            let t = (depth - self.fog_start) / util::f64_max(self.fog_end - self.fog_start, 1e-9);
+            alpha *= 1.0 - util::f64_min(util::f64_max(t, 0.0), 1.0);
+        }
+        [color[0], color[1], color[2], alpha as f32]
+    }
+}
@@ -0,0 +1,69 @@
+use std::cmp::Ordering;
+
+use pw;
+
+// A shape in the view, the result of projecting something in 3D
+#[derive(Clone)]
+pub enum Primitive {
+    // Line from (line[0], line[1]) to (line[2], line[3]), `radius` pixels
+    // thick on either side, with an arrowhead of lines `head` pixels long
+    // (if not 0) at the latter end
+    Arrow { line: [f64; 4], head: f64, radius: f64, color: [f32; 4] },
+    // Filled convex polygon
+    Polygon { points: Vec<[f64; 2]>, color: [f32; 4] },
+    // Filled circle with a thin border
+    Disc { center: [f64; 2], radius: f64, color: [f32; 4], border: [f32; 4] },
+}
+
+// Primitives to be drawn, each with its depth in front of the camera so
+// that they can be drawn farthest first
+pub struct DrawList {
+    items: Vec<(f64, Primitive)>,
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList {
+            items: vec![],
+        }
+    }
+
+    pub fn push(&mut self, depth: f64, primitive: Primitive) {
+        self.items.push((depth, primitive));
+    }
+
+    // Order the primitives so that nearer ones are drawn over farther ones.
+    // The sort is stable, so primitives at equal depth keep their order.
+    pub fn sort_back_to_front(&mut self) {
+        self.items.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    }
+
+    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d) {
+        for &(_, ref primitive) in &self.items {
+            match *primitive {
+                Primitive::Arrow { line, head, radius, color } => {
+                    let style = pw::Line::new(color, radius);
+                    if head > 0.0 {
+                        style.draw_arrow(line, head, &c.draw_state, c.transform, gl);
+                    } else {
+                        style.draw(line, &c.draw_state, c.transform, gl);
+                    }
+                },
+                Primitive::Polygon { ref points, color } => {
+                    pw::Polygon::new(color)
+                        .draw(points, &c.draw_state, c.transform, gl);
+                },
+                Primitive::Disc { center, radius, color, border } => {
+                    pw::Ellipse::new(color)
+                        .border(pw::ellipse::Border { color: border, radius: 0.5 })
+                        .draw([center[0] - radius, center[1] - radius, radius * 2.0, radius * 2.0],
+                              &c.draw_state, c.transform, gl);
+                },
+            }
+        }
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<(f64, Primitive)> {
+        self.items.iter()
+    }
+}
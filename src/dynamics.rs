@@ -0,0 +1,127 @@
+use num::Zero;
+
+use na::{Vector3, Norm};
+
+use point_charge::{PointCharge, PointChargesFieldView};
+use units::Units;
+
+use consts::*;
+
+// Integrates the motion of a set of mutually interacting PointCharges
+// using velocity Verlet, which keeps the total energy bounded over long
+// runs (unlike explicit Euler, where it drifts steadily).
+pub struct Dynamics {
+    // Added to inter-charge distances in quadrature to avoid blowups
+    // when two charges get very close
+    pub softening: f64,
+
+    // The units of the charges, which set the strength of their interaction
+    units: Units,
+
+    // Simulated time elapsed since the simulation was started
+    pub time: f64,
+
+    // Forces from the end of the previous step, reused at the start of
+    // the next one within a call to `advance`
+    forces: Vec<Vector3<f64>>,
+
+    // The charges as they were when the simulation was started, so that
+    // they can be restored
+    initial: Vec<PointCharge>,
+}
+
+// The quantities which should be conserved by the simulation
+pub struct Conserved {
+    pub kinetic: f64,
+    pub potential: f64,
+    pub momentum: Vector3<f64>,
+}
+
+impl Conserved {
+    pub fn energy(&self) -> f64 {
+        self.kinetic + self.potential
+    }
+}
+
+impl Dynamics {
+    pub fn new(charges: &[PointCharge], units: Units) -> Dynamics {
+        Dynamics {
+            softening: DYNAMICS_SOFTENING,
+            units: units,
+            time: 0.0,
+            forces: vec![],
+            initial: charges.to_vec(),
+        }
+    }
+
+    // Advance the simulation by `dt`, split into DYNAMICS_SUBSTEPS steps
+    pub fn advance(&mut self, charges: &mut [PointCharge], dt: f64) {
+        // The charges may have been edited since the last step
+        self.forces = forces(charges, self.softening, &self.units);
+        let h = dt / DYNAMICS_SUBSTEPS as f64;
+        for _ in 0..DYNAMICS_SUBSTEPS {
+            self.step(charges, h);
+        }
+    }
+
+    fn step(&mut self, charges: &mut [PointCharge], h: f64) {
+        // Half kick, then drift
+        for (chg, f) in charges.iter_mut().zip(self.forces.iter()) {
+            chg.velocity += *f * (0.5 * h / chg.mass);
+            let dx = chg.velocity * h;
+            *chg += dx;
+        }
+        // Second half kick, with the forces at the new positions
+        self.forces = forces(charges, self.softening, &self.units);
+        for (chg, f) in charges.iter_mut().zip(self.forces.iter()) {
+            chg.velocity += *f * (0.5 * h / chg.mass);
+        }
+        self.time += h;
+    }
+
+    // Put the charges back where they were when the simulation started.
+    // This is recorded in the history of `field`, which the steps of the
+    // simulation are not, so that later undos replay against the charges
+    // actually there.
+    pub fn reset(&self, field: &mut PointChargesFieldView) {
+        field.replace_charges(self.initial.clone());
+    }
+
+    pub fn conserved(&self, charges: &[PointCharge]) -> Conserved {
+        let kinetic = charges.iter()
+            .map(|chg| 0.5 * chg.mass * chg.velocity.norm_squared())
+            .fold(0.0, |a, b| a + b);
+        let momentum = charges.iter()
+            .map(|chg| chg.velocity * chg.mass)
+            .fold(Zero::zero(), |a: Vector3<f64>, b| a + b);
+        Conserved {
+            kinetic: kinetic,
+            potential: potential_energy(charges, self.softening, &self.units),
+            momentum: momentum,
+        }
+    }
+}
+
+// Net force on each charge from all of the others
+pub fn forces(charges: &[PointCharge], softening: f64, units: &Units) -> Vec<Vector3<f64>> {
+    let mut res: Vec<Vector3<f64>> = vec![Zero::zero(); charges.len()];
+    for i in 0..charges.len() {
+        for j in (i + 1)..charges.len() {
+            let f = charges[i].force_from(&charges[j], softening, units);
+            res[i] += f;
+            res[j] -= f;
+        }
+    }
+    res
+}
+
+// Total electrostatic potential energy of the configuration
+pub fn potential_energy(charges: &[PointCharge], softening: f64, units: &Units) -> f64 {
+    let mut total = 0.0;
+    for i in 0..charges.len() {
+        for j in (i + 1)..charges.len() {
+            total += charges[i].potential_energy_with(&charges[j], softening, units);
+        }
+    }
+    total
+}
@@ -0,0 +1,146 @@
+use na::Point3;
+
+use point_charge::{PointCharge, PointChargesFieldView};
+
+use consts::*;
+
+// State of the charge list panel: which charge is selected (and so
+// controlled by TFGH,RY), which page of the list is shown, and the text
+// being typed for the selected charge's values
+pub struct ChargeEditor {
+    pub selected: usize,
+    pub page: usize,
+    // Text of the x, y, z and charge boxes
+    pub text: [String; 4],
+    // Whether `text` must be refilled from the selected charge
+    stale: bool,
+}
+
+impl ChargeEditor {
+    pub fn new() -> ChargeEditor {
+        ChargeEditor {
+            selected: 0,
+            page: 0,
+            text: [String::new(), String::new(), String::new(), String::new()],
+            stale: true,
+        }
+    }
+
+    pub fn select(&mut self, i: usize) {
+        self.selected = i;
+        self.page = i / EDITOR_ROWS;
+        self.stale = true;
+    }
+
+    // Note that the selected charge was changed other than by typing,
+    // eg. by dragging it
+    pub fn reload(&mut self) {
+        self.stale = true;
+    }
+
+    pub fn page_count(&self, field: &PointChargesFieldView) -> usize {
+        (field.charges.len() + EDITOR_ROWS - 1) / EDITOR_ROWS
+    }
+
+    // Keep the selection within the charges of `field`, and refill the
+    // text boxes if needed
+    pub fn sync(&mut self, field: &PointChargesFieldView) {
+        if field.charges.is_empty() {
+            self.selected = 0;
+            self.page = 0;
+            return;
+        }
+        if self.selected >= field.charges.len() {
+            self.select(field.charges.len() - 1);
+        }
+        if self.page >= self.page_count(field) {
+            self.page = self.page_count(field) - 1;
+        }
+        if self.stale {
+            let chg = &field.charges[self.selected];
+            self.text = [
+                format!("{:.2}", chg.loc.x),
+                format!("{:.2}", chg.loc.y),
+                format!("{:.2}", chg.loc.z),
+                format!("{:.2}", chg.charge),
+            ];
+            self.stale = false;
+        }
+    }
+
+    // Apply the text of box `which` (0-2 for x-z, 3 for the charge) to the
+    // selected charge. Returns whether the charge was changed.
+    pub fn apply(&mut self, field: &mut PointChargesFieldView, which: usize) -> bool {
+        if self.selected >= field.charges.len() {
+            return false;
+        }
+        let value = match self.text[which].trim().parse::<f64>() {
+            Ok(value) => value,
+            Err(_) => {
+                // Restore the current value
+                self.stale = true;
+                return false;
+            },
+        };
+        let i = self.selected;
+        if which == 3 {
+            field.set_charge(i, value);
+        } else {
+            let mut loc = field.charges[i].loc;
+            match which {
+                0 => loc.x = value,
+                1 => loc.y = value,
+                _ => loc.z = value,
+            }
+            field.set_charge_loc(i, loc);
+        }
+        self.stale = true;
+        true
+    }
+
+    // Add a new unit charge at the origin and select it
+    pub fn add(&mut self, field: &mut PointChargesFieldView) {
+        let i = field.add_charge(PointCharge::new(1.0, Point3::new(0.0, 0.0, 0.0)));
+        self.select(i);
+    }
+
+    pub fn remove(&mut self, field: &mut PointChargesFieldView) {
+        if self.selected < field.charges.len() {
+            field.remove_charge(self.selected);
+            let i = if self.selected > 0 { self.selected - 1 } else { 0 };
+            self.select(i);
+        }
+    }
+
+    // Add a copy of the selected charge, offset so that it is visible
+    pub fn duplicate(&mut self, field: &mut PointChargesFieldView) {
+        if self.selected < field.charges.len() {
+            let mut chg = field.charges[self.selected].clone();
+            chg.loc.x += CHARGE_MVMT_STEP;
+            let i = field.add_charge(chg);
+            self.select(i);
+        }
+    }
+
+    // Add the mirror image of the selected charge in the plane x = 0
+    pub fn mirror(&mut self, field: &mut PointChargesFieldView) {
+        if self.selected < field.charges.len() {
+            let mut chg = field.charges[self.selected].clone();
+            chg.loc.x = -chg.loc.x;
+            let i = field.add_charge(chg);
+            self.select(i);
+        }
+    }
+
+    pub fn toggle_visible(&mut self, field: &mut PointChargesFieldView, i: usize) {
+        let visible = field.charges[i].visible;
+        field.set_charge_visible(i, !visible);
+    }
+}
+
+// One-line description of a charge for the list
+pub fn describe(i: usize, chg: &PointCharge) -> String {
+    format!("#{} {:+.1} at ({:.1}, {:.1}, {:.1}){}",
+            i, chg.charge, chg.loc.x, chg.loc.y, chg.loc.z,
+            if chg.visible { "" } else { " (hidden)" })
+}
@@ -0,0 +1,120 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use na::{Vector3, Norm};
+
+use super::{Bounds, GridPoint, VectorField, uniform_grid};
+
+// Cells are split at most this many times, ie. down to 1/8 of the base spacing
+const MAX_REFINEMENT: u32 = 3;
+// Cells across which the field varies less than this are never split
+const MIN_VARIATION: f64 = 0.05;
+
+// A cube of the grid, centered on its arrow
+struct Cell {
+    pt: GridPoint,
+    level: u32,
+    // How much the field varies across the cell, in [0.0, 1.0]
+    variation: f64,
+}
+
+impl Cell {
+    fn new(field: &VectorField, pt: GridPoint, level: u32) -> Cell {
+        Cell {
+            pt: pt,
+            level: level,
+            variation: variation(field, &pt),
+        }
+    }
+}
+
+// Cells are ordered by variation so that the most rapidly varying one is
+// at the top of the heap
+impl PartialEq for Cell {
+    fn eq(&self, other: &Cell) -> bool {
+        self.variation == other.variation
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Cell) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Cell) -> Ordering {
+        self.variation.partial_cmp(&other.variation).unwrap_or(Ordering::Equal)
+    }
+}
+
+// Grid points which start as a regular grid of the given spacing and are
+// refined where the field varies most (eg. near charges), splitting a
+// cell into eight half-size cells at a time, until there are `budget`
+// points in total
+pub fn adaptive_grid(field: &VectorField, bounds: &Bounds, spacing: f64, budget: usize) -> Vec<GridPoint> {
+    let coarse = uniform_grid(bounds, spacing);
+    if coarse.len() >= budget {
+        return coarse;
+    }
+
+    let mut count = coarse.len();
+    let mut heap: BinaryHeap<Cell> = coarse.into_iter().map(|pt| Cell::new(field, pt, 0)).collect();
+    let mut done = vec![];
+    // Each split replaces one point with eight
+    while count + 7 <= budget {
+        let cell = match heap.pop() {
+            Some(cell) => cell,
+            None => break,
+        };
+        if cell.variation < MIN_VARIATION {
+            // No remaining cell varies enough to be worth splitting
+            done.push(cell.pt);
+            break;
+        }
+        if cell.level >= MAX_REFINEMENT {
+            done.push(cell.pt);
+            continue;
+        }
+        let q = cell.pt.spacing * 0.25;
+        for i in 0..8 {
+            let offset = Vector3::new(
+                if i & 1 != 0 { q } else { -q },
+                if i & 2 != 0 { q } else { -q },
+                if i & 4 != 0 { q } else { -q });
+            let child = GridPoint {
+                loc: cell.pt.loc + offset,
+                spacing: cell.pt.spacing * 0.5,
+            };
+            heap.push(Cell::new(field, child, cell.level + 1));
+        }
+        count += 7;
+    }
+    done.extend(heap.into_iter().map(|cell| cell.pt));
+
+    // Heap order is arbitrary; sort so that the result is reproducible
+    done.sort_by(|a, b| {
+        (a.loc.x, a.loc.y, a.loc.z).partial_cmp(&(b.loc.x, b.loc.y, b.loc.z)).unwrap_or(Ordering::Equal)
+    });
+    done
+}
+
+// Largest relative difference between the field at the center of the cell
+// and the field at the centers of its faces
+fn variation(field: &VectorField, pt: &GridPoint) -> f64 {
+    let center = field.field_data_at(&pt.loc).force_vec;
+    let h = pt.spacing * 0.5;
+    let offsets = [
+        Vector3::new(h, 0.0, 0.0), Vector3::new(-h, 0.0, 0.0),
+        Vector3::new(0.0, h, 0.0), Vector3::new(0.0, -h, 0.0),
+        Vector3::new(0.0, 0.0, h), Vector3::new(0.0, 0.0, -h),
+    ];
+    offsets.iter()
+        .map(|offset| {
+            let sample = field.field_data_at(&(pt.loc + *offset)).force_vec;
+            (sample - center).norm() / (sample.norm() + center.norm() + 1e-12)
+        })
+        .fold(0.0, |a: f64, b| a.max(b))
+}
@@ -1,25 +1,96 @@
-use na::{Point3, Matrix4, Norm};
+use na::{Point3, Norm};
 
 use pw;
 
-use arrow::Arrow;
+use rayon::prelude::*;
+
+use arrow::{Arrow, ArrowStyle};
+use point_charge::PointChargesFieldView;
+use pose::Pose;
+use projection::Projection;
+use depth_cue::DepthCue;
+use draw_list::DrawList;
+use units::Units;
 
 mod vector_field;
 pub use self::vector_field::*;
 
+mod adaptive;
+pub use self::adaptive::*;
+
+use util;
 use consts::*;
 
-pub trait FieldView: VectorField {
-    // Each range incl. on lower bound, excl. on upper bound
-    fn ranges(&self) -> ((i64, i64), (i64, i64), (i64, i64));
+// A point at which an arrow is drawn, along with the spacing of the grid
+// around it (which bounds the length of the arrow)
+#[derive(Clone, Copy, PartialEq)]
+pub struct GridPoint {
+    pub loc: Point3<f64>,
+    pub spacing: f64,
+}
+
+// Bounds of a grid in x, y and z. Each range incl. on lower bound, excl. on upper bound
+pub type Bounds = [(f64, f64); 3];
 
-    fn transform_arrows(&mut self, Matrix4<f64>);
+// FieldViews are shared across threads while populating the field
+pub trait FieldView: VectorField + Sync {
+    // The region in which arrows are drawn
+    fn bounds(&self) -> Bounds;
+    // Distance between neighboring arrows
+    fn grid_spacing(&self) -> f64 {
+        GRID_S
+    }
+
+    fn transform_arrows(&mut self, &Pose);
     fn reapply_arrow_transforms(&mut self);
-    fn transform_camera(&mut self, Matrix4<f64>);
+    fn transform_camera(&mut self, &Pose);
+    // Return the camera to where it was when the FieldView was created
+    fn reset_camera(&mut self);
+
+    // The product of all transformations applied to the arrows, and the
+    // transformation from the arrows' coordinates to the camera's
+    fn arrow_transforms(&self) -> Pose;
+    fn camera(&self) -> Pose;
+    fn set_camera(&mut self, Pose);
+
+    // How the camera's view is mapped onto the screen
+    fn projection(&self) -> &Projection;
+    fn projection_mut(&mut self) -> &mut Projection;
+
+    // How depth is conveyed when rendering
+    fn depth_cue(&self) -> &DepthCue;
+    fn depth_cue_mut(&mut self) -> &mut DepthCue;
+    fn arrow_style(&self) -> ArrowStyle;
+    fn set_arrow_style(&mut self, ArrowStyle);
 
     fn set_arrows(&mut self, Vec<Arrow>);
+    fn arrows(&self) -> &[Arrow];
+
+    // The view as a field of point charges, if it is one, for the tools
+    // which edit and simulate charges
+    fn point_charges(&self) -> Option<&PointChargesFieldView> {
+        None
+    }
+    fn point_charges_mut(&mut self) -> Option<&mut PointChargesFieldView> {
+        None
+    }
+
+    // What the numbers of the field mean physically, for readouts
+    fn units(&self) -> Units {
+        Units::legacy()
+    }
+
+    // Called before the field is (re)populated, eg. to rebuild
+    // acceleration structures after the sources have changed
+    fn prepare_field(&mut self) {}
 
-    fn render(&self, c: pw::Context, gl: &mut pw::G2d, view: [f64; 4]);
+    // The arrows as seen in the section `view`, ready to be drawn
+    fn draw_list(&self, view: [f64; 4]) -> DrawList;
+
+    // Draw the arrows over the (already cleared) section `view`
+    fn render(&self, c: pw::Context, gl: &mut pw::G2d, view: [f64; 4]) {
+        self.draw_list(view).draw(c, gl);
+    }
 
     // `greatest_*` used for neat rendering:
     // field value which will correspond to the longest arrow that fits in the grid (ie., the
@@ -31,44 +102,92 @@ pub trait FieldView: VectorField {
     // potential value for lightest color
     fn least_pot(&self) -> f64;
 
-    fn populate_field(&mut self) {
-        // Each range incl. on lower bound, excl. on upper bound
-        let ((min_x, max_x), (min_y, max_y), (min_z, max_z)) = self.ranges();
-
-        let mut arrows = vec![];
-
-        // Generate arrows for each position in field
-        for i in min_x..max_x {
-            for j in min_y..max_y {
-                for k in min_z..max_z {
-                    let loc = Point3::new(
-                        i as f64 * GRID_S,
-                        j as f64 * GRID_S,
-                        k as f64 * GRID_S);
-                    let field_data = self.field_data_at(&loc);
-
-                    let rel_field = field_data.force_mag / self.greatest_field();
-
-                    // Map space of potential values from range
-                    // [least_pot, greatest_pot] to range [0.0, 1.0]
-                    let rel_pot = (field_data.potential - self.least_pot()) / (self.greatest_pot() - self.least_pot());
-
-                    let length = FIELD_VEC_MIN_LEN + rel_field * FIELD_VEC_LEN_RANGE;
-
-                    // loc is the center of the arrow stem
-                    let arrow_vec = length * field_data.force_vec.normalize();
-                    let tail = loc - arrow_vec * 0.5;
-                    let head = loc + arrow_vec * 0.5;
-                    arrows.push(Arrow {
-                        tail: tail,
-                        head: head,
-                        field: rel_field,
-                        potential: rel_pot,
-                    });
-                }
-            }
+    // The points at which arrows are drawn
+    fn grid_points(&self) -> Vec<GridPoint> {
+        uniform_grid(&self.bounds(), self.grid_spacing())
+    }
+
+    // Generate the arrow for the field at `pt`
+    fn arrow_at(&self, pt: &GridPoint) -> Arrow {
+        self.arrow_for(pt, &self.field_data_at(&pt.loc))
+    }
+
+    // Generate the arrow at `pt` for an already computed `field_data`
+    fn arrow_for(&self, pt: &GridPoint, field_data: &FieldData) -> Arrow {
+        let rel_field = field_data.force_mag / self.greatest_field();
+
+        // Map space of potential values from range
+        // [least_pot, greatest_pot] to range [0.0, 1.0]
+        let rel_pot = (field_data.potential - self.least_pot()) / (self.greatest_pot() - self.least_pot());
+
+        // The FIELD_VEC_* lengths are for a grid of spacing GRID_S
+        let length = (FIELD_VEC_MIN_LEN + rel_field * FIELD_VEC_LEN_RANGE) * pt.spacing / GRID_S;
+
+        // pt.loc is the center of the arrow stem
+        let arrow_vec = length * field_data.force_vec.normalize();
+        let tail = pt.loc - arrow_vec * 0.5;
+        let head = pt.loc + arrow_vec * 0.5;
+        Arrow {
+            tail: tail,
+            head: head,
+            field: rel_field,
+            potential: rel_pot,
         }
+    }
+
+    // Bring the arrows up to date after the sources of the field have
+    // changed. Views which can do so incrementally override this.
+    fn rebuild(&mut self) {
+        self.populate_field();
+    }
+
+    // Generate arrows for each position in field. Grid points are split
+    // across threads; `collect` keeps the arrows in grid order, so the
+    // result is the same as that of `populate_field_serial`.
+    fn populate_field(&mut self) {
+        self.prepare_field();
+        let arrows = {
+            let pts = self.grid_points();
+            pts.par_iter()
+                .map(|pt| self.arrow_at(pt))
+                .collect()
+        };
+        self.set_arrows(arrows);
+    }
 
+    // Single-threaded equivalent of `populate_field`, for comparison
+    fn populate_field_serial(&mut self) {
+        self.prepare_field();
+        let arrows = {
+            let pts = self.grid_points();
+            pts.iter()
+                .map(|pt| self.arrow_at(pt))
+                .collect()
+        };
         self.set_arrows(arrows);
     }
 }
+
+// The points of a regular grid of the given spacing filling `bounds`
+pub fn uniform_grid(bounds: &Bounds, spacing: f64) -> Vec<GridPoint> {
+    // Number of points along each axis. The small epsilon keeps the upper
+    // bound exclusive despite rounding.
+    let count = |(lo, hi): (f64, f64)| util::f64_max(((hi - lo) / spacing - 1e-9).ceil(), 0.0) as i64;
+    let (nx, ny, nz) = (count(bounds[0]), count(bounds[1]), count(bounds[2]));
+
+    let mut pts = vec![];
+    for i in 0..nx {
+        for j in 0..ny {
+            for k in 0..nz {
+                pts.push(GridPoint {
+                    loc: Point3::new(
+                        bounds[0].0 + i as f64 * spacing,
+                        bounds[1].0 + j as f64 * spacing,
+                        bounds[2].0 + k as f64 * spacing),
+                    spacing: spacing,
+                });
+            }
+        }
+    }
+    pts
+}
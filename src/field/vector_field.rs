@@ -1,12 +1,13 @@
 use num::Zero;
 use na::{Point3, Vector3, Norm};
 
-use std::ops::Add;
+use std::ops::{Add, Sub};
 
 pub trait VectorField {
     fn field_data_at(&self, p: &Point3<f64>) -> FieldData;
 }
 
+#[derive(Clone, Copy)]
 pub struct FieldData {
     pub force_vec: Vector3<f64>, // direction and (unscaled) strength of field
     pub force_mag: f64, // cached norm of force_vec, updated manually
@@ -48,3 +49,14 @@ impl Add<FieldData> for FieldData {
         )
     }
 }
+
+impl Sub<FieldData> for FieldData {
+    type Output = FieldData;
+    fn sub(self, right: FieldData) -> FieldData {
+        FieldData::new(
+            self.force_vec - right.force_vec,
+            self.force_mag, // not updated
+            self.potential - right.potential
+        )
+    }
+}
@@ -0,0 +1,115 @@
+use pw;
+
+use na::{self, Point3, Vector3, Norm};
+
+use field::FieldView;
+use point_charge::PointChargesFieldView;
+use dynamics;
+use draw_list::{DrawList, Primitive};
+use units::Quantity;
+
+use util;
+use consts::*;
+
+// The electrostatic potential energy of a configuration of charges, and the
+// net force and torque on each charge from all of the others. Unlike the
+// simulation, the forces are not softened, so they are exact (and infinite
+// for coinciding charges).
+pub struct ForceReport {
+    pub energy: f64,
+    // Indexed like the charges; zero for hidden charges
+    pub forces: Vec<Vector3<f64>>,
+    // Torque of each force about `center`
+    pub torques: Vec<Vector3<f64>>,
+    // The centroid of the visible charges
+    pub center: Point3<f64>,
+}
+
+impl ForceReport {
+    pub fn new(field: &PointChargesFieldView) -> ForceReport {
+        let forces = dynamics::forces(&field.charges, 0.0, &field.units);
+        let visible: Vec<Vector3<f64>> = field.charges.iter()
+            .filter(|chg| chg.visible)
+            .map(|chg| chg.loc.to_vector())
+            .collect();
+        let sum = visible.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, v| sum + *v);
+        let center = Point3::new(0.0, 0.0, 0.0) + sum / util::f64_max(visible.len() as f64, 1.0);
+        let torques = field.charges.iter().zip(forces.iter())
+            .map(|(chg, f)| na::cross(&(chg.loc - center), f))
+            .collect();
+        ForceReport {
+            energy: dynamics::potential_energy(&field.charges, 0.0, &field.units),
+            forces: forces,
+            torques: torques,
+            center: center,
+        }
+    }
+
+    // The sum of the torques, which vanishes (up to rounding) since the
+    // forces between each pair of charges are equal, opposite and along the
+    // line joining them
+    pub fn net_torque(&self) -> Vector3<f64> {
+        self.torques.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, t| sum + *t)
+    }
+
+    // The energy and net torque, for display
+    pub fn summary(&self, field: &PointChargesFieldView) -> String {
+        let units = field.units;
+        let torque = |x| units.format(x, Quantity::Torque);
+        let t = self.net_torque();
+        format!("Potential energy: {}\nNet torque: ({}, {}, {})",
+                units.format(self.energy, Quantity::Energy), torque(t.x), torque(t.y), torque(t.z))
+    }
+
+    // The force and torque on charge `i`, for display
+    pub fn readout(&self, field: &PointChargesFieldView, i: usize) -> String {
+        let units = field.units;
+        let force = |x| units.format(x, Quantity::Force);
+        let torque = |x| units.format(x, Quantity::Torque);
+        let (f, t) = (self.forces[i], self.torques[i]);
+        format!("#{} F = ({}, {}, {}), |F| = {}\nTorque about centroid: ({}, {}, {})",
+                i, force(f.x), force(f.y), force(f.z), force(f.norm()), torque(t.x), torque(t.y), torque(t.z))
+    }
+
+    // An arrow from each charge in the direction of the force on it. The
+    // longest is FORCE_ARROW_LENGTH long, and the others are scaled alike.
+    pub fn draw_list(&self, field: &PointChargesFieldView, view: [f64; 4]) -> DrawList {
+        let mut list = DrawList::new();
+        let greatest = self.forces.iter()
+            .map(|f| f.norm())
+            .filter(|f| f.is_finite())
+            .fold(0.0, util::f64_max);
+        if greatest <= 0.0 {
+            return list;
+        }
+        let to_camera = field.arrow_transforms().then(&field.camera());
+        let projection = field.projection();
+        for (chg, f) in field.charges.iter().zip(self.forces.iter()) {
+            let magnitude = f.norm();
+            if !chg.visible || !magnitude.is_finite() || magnitude <= 0.0 {
+                continue;
+            }
+            let tail = to_camera.transform_point(&chg.loc);
+            let tip = to_camera.transform_point(&(chg.loc + *f * (FORCE_ARROW_LENGTH / greatest)));
+            // Only the part in view is drawn, and the arrowhead only if the
+            // tip is in view, as for the arrows of the field
+            if let Some((t0, t1)) = projection.clip_segment(&tail, &tip, view) {
+                let d = tip - tail;
+                let (from, to) = (tail + d * t0, tail + d * t1);
+                let (a, b) = (projection.to_viewport(&from, view), projection.to_viewport(&to, view));
+                list.push(from.z, Primitive::Arrow {
+                    line: [a[0], a[1], b[0], b[1]],
+                    head: if t1 < 1.0 { 0.0 } else { FORCE_ARROW_HEAD },
+                    radius: FORCE_ARROW_RADIUS,
+                    color: FORCE_ARROW_COLOR,
+                });
+            }
+        }
+        list.sort_back_to_front();
+        list
+    }
+
+    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d, field: &PointChargesFieldView, view: [f64; 4]) {
+        self.draw_list(field, view).draw(c, gl);
+    }
+}
@@ -0,0 +1,175 @@
+use na::{Point3, Vector3, Quaternion, UnitQuaternion};
+
+use pose::{self, Pose};
+
+use consts::*;
+
+// A pose of the camera (as returned by FieldView::camera) at a time
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub camera: Pose,
+}
+
+impl Keyframe {
+    // Parse the values of a `keyframe:` line of a scene file:
+    //     <time> <qw> <qi> <qj> <qk> <x> <y> <z>
+    // ie. the time, then the camera's rotation and translation
+    pub fn parse(values: &str) -> Result<Keyframe, String> {
+        let v: Vec<f64> = try!(values.split_whitespace()
+            .map(|s| s.parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| "expected numbers after `keyframe`".to_string()));
+        if v.len() != 8 {
+            return Err("expected 8 numbers after `keyframe`".to_string());
+        }
+        Ok(Keyframe {
+            time: v[0],
+            camera: Pose {
+                rotation: UnitQuaternion::new_with_quaternion(Quaternion::new(v[1], v[2], v[3], v[4])),
+                translation: Vector3::new(v[5], v[6], v[7]),
+            },
+        })
+    }
+
+    // The keyframe as a line of a scene file, as read by `parse`
+    pub fn to_line(&self) -> String {
+        let (q, t) = (self.camera.rotation.quaternion(), self.camera.translation);
+        format!("keyframe: {} {} {} {} {} {} {} {}", self.time, q.w, q.i, q.j, q.k, t.x, t.y, t.z)
+    }
+
+    // Where the camera is, in the coordinates of the arrows
+    fn eye(&self) -> Point3<f64> {
+        Point3::new(0.0, 0.0, 0.0) + self.camera.inverse().translation
+    }
+}
+
+// Keyframed camera poses for fly-throughs. Between keyframes the camera's
+// orientation is interpolated by slerp, and its position along a
+// Catmull-Rom spline through the keyframes' positions, so that it does not
+// turn sharply at each keyframe.
+pub struct CameraPath {
+    // In order of time
+    keyframes: Vec<Keyframe>,
+    // Time along the path while it is played
+    pub time: f64,
+    pub playing: bool,
+    // Start again from the first keyframe at the end
+    pub looping: bool,
+}
+
+impl CameraPath {
+    pub fn new() -> CameraPath {
+        CameraPath {
+            keyframes: vec![],
+            time: 0.0,
+            playing: false,
+            looping: false,
+        }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    // Time of the last keyframe
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    // Add a keyframe, keeping them in order of time
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        let i = self.keyframes.iter().position(|k| k.time > keyframe.time).unwrap_or(self.keyframes.len());
+        self.keyframes.insert(i, keyframe);
+    }
+
+    // Add `camera` as a keyframe KEYFRAME_INTERVAL after the last one
+    pub fn record(&mut self, camera: Pose) {
+        let time = if self.keyframes.is_empty() { 0.0 } else { self.duration() + KEYFRAME_INTERVAL };
+        self.insert(Keyframe { time: time, camera: camera });
+    }
+
+    pub fn remove_last(&mut self) {
+        self.keyframes.pop();
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.stop();
+    }
+
+    pub fn play(&mut self) {
+        if self.keyframes.len() >= 2 {
+            self.time = 0.0;
+            self.playing = true;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    // Move `dt` seconds along the path while playing, returning the pose
+    // of the camera at the new time
+    pub fn advance(&mut self, dt: f64) -> Option<Pose> {
+        if !self.playing {
+            return None;
+        }
+        self.time += dt;
+        if self.time > self.duration() {
+            if self.looping && self.duration() > 0.0 {
+                self.time %= self.duration();
+            } else {
+                self.time = self.duration();
+                self.playing = false;
+            }
+        }
+        self.camera_at(self.time)
+    }
+
+    // The pose of the camera at time `t`, if there are any keyframes.
+    // Before the first and after the last keyframe, the camera stays put.
+    pub fn camera_at(&self, t: f64) -> Option<Pose> {
+        let keys = &self.keyframes;
+        let n = keys.len();
+        if n == 0 {
+            return None;
+        }
+        if n == 1 || t <= keys[0].time {
+            return Some(keys[0].camera);
+        }
+        if t >= keys[n - 1].time {
+            return Some(keys[n - 1].camera);
+        }
+        // Keyframes i and i + 1 are either side of t
+        let i = keys.iter().rposition(|k| k.time <= t).unwrap_or(0);
+        let span = keys[i + 1].time - keys[i].time;
+        let s = if span > 0.0 { (t - keys[i].time) / span } else { 1.0 };
+
+        let rotation = pose::slerp(&keys[i].camera.rotation, &keys[i + 1].camera.rotation, s);
+        // The ends are repeated where there is no keyframe beyond them
+        let eye = |j: usize| keys[j].eye().to_vector();
+        let (p0, p1, p2, p3) = (eye(if i > 0 { i - 1 } else { i }), eye(i), eye(i + 1), eye(if i + 2 < n { i + 2 } else { i + 1 }));
+        let position = catmull_rom(p0, p1, p2, p3, s);
+        // The camera maps `position` to the origin
+        let rotation_pose = Pose::rotation(rotation);
+        Some(Pose {
+            rotation: rotation,
+            translation: -rotation_pose.rotate(&position),
+        })
+    }
+}
+
+// Point at `s` in [0.0, 1.0] along the (uniform) Catmull-Rom segment from
+// p1 to p2
+fn catmull_rom(p0: Vector3<f64>, p1: Vector3<f64>, p2: Vector3<f64>, p3: Vector3<f64>, s: f64) -> Vector3<f64> {
+    let (s2, s3) = (s * s, s * s * s);
+    (p1 * 2.0
+        + (p2 - p0) * s
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * s2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * s3) * 0.5
+}
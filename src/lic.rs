@@ -0,0 +1,248 @@
+use std::path::Path;
+
+use pw::{self, Transformed};
+
+use image::RgbaImage;
+
+use rayon::prelude::*;
+
+use na::{self, Point3, Vector3, Norm};
+
+use field::{FieldView, VectorField, Bounds};
+use pose::Pose;
+
+use util::{self, Rng};
+use consts::*;
+
+// Orientation of a slice through the grid, named by the axes it spans
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SliceAxes {
+    XY,
+    XZ,
+    YZ,
+}
+
+impl SliceAxes {
+    pub fn next(&self) -> SliceAxes {
+        match *self {
+            SliceAxes::XY => SliceAxes::XZ,
+            SliceAxes::XZ => SliceAxes::YZ,
+            SliceAxes::YZ => SliceAxes::XY,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SliceAxes::XY => "xy",
+            SliceAxes::XZ => "xz",
+            SliceAxes::YZ => "yz",
+        }
+    }
+
+    // Indices of the two axes spanned, and of the normal
+    fn axes(&self) -> (usize, usize, usize) {
+        match *self {
+            SliceAxes::XY => (0, 1, 2),
+            SliceAxes::XZ => (0, 2, 1),
+            SliceAxes::YZ => (1, 2, 0),
+        }
+    }
+}
+
+// A rectangle through the grid, perpendicular to an axis, on which the
+// field's direction is shown by line integral convolution (LIC): noise is
+// smeared along the field lines, leaving streaks which follow them
+pub struct LicSlice {
+    pub axes: SliceAxes,
+    // Position of the slice along its normal, as a fraction of the grid's
+    // extent
+    pub offset: f64,
+    // Texels across the wider side of the slice
+    pub resolution: u32,
+
+    image: Option<RgbaImage>,
+    texture: Option<pw::G2dTexture<'static>>,
+}
+
+impl LicSlice {
+    pub fn new() -> LicSlice {
+        LicSlice {
+            axes: SliceAxes::XY,
+            offset: 0.5,
+            resolution: LIC_RESOLUTION,
+            image: None,
+            texture: None,
+        }
+    }
+
+    // Discard the texture, eg. after the field or the slice changed
+    pub fn invalidate(&mut self) {
+        self.image = None;
+        self.texture = None;
+    }
+
+    // Compute the texture for `field` if it is out of date. Returns whether
+    // it was recomputed.
+    pub fn update(&mut self, field: &FieldView) -> bool {
+        if self.image.is_some() {
+            return false;
+        }
+        self.image = Some(self.compute(field));
+        true
+    }
+
+    // Make the texture available for drawing, if it is computed
+    pub fn upload(&mut self, factory: &mut pw::GfxFactory) {
+        if let (&Some(ref image), true) = (&self.image, self.texture.is_none()) {
+            self.texture = pw::Texture::from_image(factory, image, &pw::TextureSettings::new()).ok();
+        }
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), String> {
+        match self.image {
+            Some(ref image) => image.save(path).map_err(|e| e.to_string()),
+            None => Err("the texture has not been computed".to_string()),
+        }
+    }
+
+    // Corner of the slice and the vectors along its sides, in the
+    // coordinates of the grid
+    fn frame(&self, bounds: &Bounds) -> (Point3<f64>, Vector3<f64>, Vector3<f64>) {
+        let (a, b, n) = self.axes.axes();
+        let mut corner = [bounds[0].0, bounds[1].0, bounds[2].0];
+        corner[n] = bounds[n].0 + (bounds[n].1 - bounds[n].0) * self.offset;
+        let mut u = [0.0; 3];
+        let mut v = [0.0; 3];
+        u[a] = bounds[a].1 - bounds[a].0;
+        v[b] = bounds[b].1 - bounds[b].0;
+        (Point3::new(corner[0], corner[1], corner[2]),
+         Vector3::new(u[0], u[1], u[2]),
+         Vector3::new(v[0], v[1], v[2]))
+    }
+
+    // Width and height of the texture, in texels
+    fn size(&self, bounds: &Bounds) -> (usize, usize) {
+        let (a, b, _) = self.axes.axes();
+        let (w, h) = (bounds[a].1 - bounds[a].0, bounds[b].1 - bounds[b].0);
+        let scale = self.resolution as f64 / util::f64_max(w, h);
+        (util::f64_max((w * scale).round(), 1.0) as usize, util::f64_max((h * scale).round(), 1.0) as usize)
+    }
+
+    fn compute(&self, field: &FieldView) -> RgbaImage {
+        let bounds = field.bounds();
+        let (corner, u, v) = self.frame(&bounds);
+        let (w, h) = self.size(&bounds);
+        let (u_hat, v_hat) = (u.normalize(), v.normalize());
+
+        // The field's direction within the slice, and its potential, at the
+        // center of each texel. Streamlines are traced through this grid
+        // rather than the field itself, which may be costly to evaluate.
+        let samples: Vec<([f64; 2], f64)> = (0..w * h).into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % w, i / w);
+                let p = corner + u * ((x as f64 + 0.5) / w as f64) + v * ((y as f64 + 0.5) / h as f64);
+                let data = field.field_data_at(&p);
+                let (du, dv) = (na::dot(&data.force_vec, &u_hat), na::dot(&data.force_vec, &v_hat));
+                let len = (du * du + dv * dv).sqrt();
+                let dir = if len > 1e-12 { [du / len, dv / len] } else { [0.0, 0.0] };
+                (dir, data.potential)
+            })
+            .collect();
+
+        let mut rng = Rng::new(LIC_NOISE_SEED);
+        let noise: Vec<f64> = (0..w * h).map(|_| rng.next_f64()).collect();
+
+        let (least_pot, greatest_pot) = (field.least_pot(), field.greatest_pot());
+        let texels: Vec<Vec<u8>> = (0..h).into_par_iter()
+            .map(|y| {
+                let mut row = Vec::with_capacity(w * 4);
+                for x in 0..w {
+                    let lic = convolve(&samples, &noise, w, h, x, y);
+                    // Averaging flattens the noise towards 0.5; stretch it back
+                    let value = util::f64_min(util::f64_max(0.5 + (lic - 0.5) * LIC_CONTRAST, 0.0), 1.0);
+                    let rel_pot = (samples[y * w + x].1 - least_pot) / (greatest_pot - least_pot);
+                    let color = potential_color(rel_pot);
+                    let brightness = 0.25 + 0.75 * value;
+                    for c in 0..3 {
+                        row.push((color[c] * brightness * 255.0) as u8);
+                    }
+                    row.push(255);
+                }
+                row
+            })
+            .collect();
+        let pixels: Vec<u8> = texels.into_iter().flat_map(|row| row.into_iter()).collect();
+        RgbaImage::from_raw(w as u32, h as u32, pixels).unwrap()
+    }
+
+    // Draw the texture on the slice. The texture is drawn in tiles, each
+    // mapped affinely onto the projection of its corners, which closely
+    // approximates the perspective mapping of the whole slice.
+    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d, field: &FieldView, view: [f64; 4]) {
+        let texture = match self.texture {
+            Some(ref texture) => texture,
+            None => return,
+        };
+        let (corner, u, v) = self.frame(&field.bounds());
+        let (w, h) = self.size(&field.bounds());
+        let to_camera: Pose = field.arrow_transforms().then(&field.camera());
+        let projection = field.projection();
+        let (tiles_u, tiles_v) = (LIC_TILES, LIC_TILES);
+        let (tile_w, tile_h) = (w as f64 / tiles_u as f64, h as f64 / tiles_v as f64);
+        let project = |s: f64, t: f64| {
+            let p = to_camera.transform_point(&(corner + u * s + v * t));
+            if projection.in_front(&p) { Some(projection.to_viewport(&p, view)) } else { None }
+        };
+        for i in 0..tiles_u {
+            for j in 0..tiles_v {
+                let (s0, t0) = (i as f64 / tiles_u as f64, j as f64 / tiles_v as f64);
+                let (s1, t1) = ((i + 1) as f64 / tiles_u as f64, (j + 1) as f64 / tiles_v as f64);
+                let (p00, p10, p01) = match (project(s0, t0), project(s1, t0), project(s0, t1)) {
+                    (Some(p00), Some(p10), Some(p01)) => (p00, p10, p01),
+                    _ => continue,
+                };
+                // Map the tile's texels, (0, 0) to (tile_w, tile_h), onto the
+                // parallelogram at p00 spanned by p10 - p00 and p01 - p00
+                let m = [
+                    [(p10[0] - p00[0]) / tile_w, (p01[0] - p00[0]) / tile_h, p00[0]],
+                    [(p10[1] - p00[1]) / tile_w, (p01[1] - p00[1]) / tile_h, p00[1]],
+                ];
+                pw::Image::new()
+                    .src_rect([s0 * w as f64, t0 * h as f64, tile_w, tile_h])
+                    .rect([0.0, 0.0, tile_w, tile_h])
+                    .draw(texture, &c.draw_state, c.transform.append_transform(m), gl);
+            }
+        }
+    }
+}
+
+// Average of the noise along the streamline through texel (x, y),
+// traced LIC_STEPS texels in each direction
+fn convolve(samples: &[([f64; 2], f64)], noise: &[f64], w: usize, h: usize, x: usize, y: usize) -> f64 {
+    let mut sum = noise[y * w + x];
+    let mut count = 1;
+    for &sign in &[1.0, -1.0] {
+        let (mut px, mut py) = (x as f64 + 0.5, y as f64 + 0.5);
+        for _ in 0..LIC_STEPS {
+            let (ix, iy) = (px as usize, py as usize);
+            let dir = samples[iy * w + ix].0;
+            if dir == [0.0, 0.0] {
+                break;
+            }
+            px += sign * dir[0];
+            py += sign * dir[1];
+            if px < 0.0 || py < 0.0 || px >= w as f64 || py >= h as f64 {
+                break;
+            }
+            sum += noise[py as usize * w + px as usize];
+            count += 1;
+        }
+    }
+    sum / count as f64
+}
+
+// Color for a potential mapped to [0.0, 1.0], from blue (low) to red (high)
+pub fn potential_color(rel_pot: f64) -> [f64; 3] {
+    let t = util::f64_min(util::f64_max(rel_pot, 0.0), 1.0);
+    [0.15 + 0.75 * t, 0.3, 0.9 - 0.75 * t]
+}
@@ -3,18 +3,74 @@ extern crate piston_window as pw;
 #[macro_use] extern crate conrod;
 extern crate num;
 extern crate nalgebra as na;
+extern crate rayon;
+extern crate image;
+extern crate gif;
 
 use pw::EventLoop;
 
 use std::f64::consts::PI;
 
 mod arrow;
+use arrow::ArrowStyle;
 
 mod field;
 use field::FieldView;
 
 mod point_charge;
-use point_charge::{PointCharge, PointChargesFieldView};
+
+mod dynamics;
+use dynamics::Dynamics;
+
+mod bench;
+
+mod pose;
+use pose::Pose;
+
+mod projection;
+use projection::ProjectionKind;
+
+mod depth_cue;
+mod draw_list;
+
+mod camera;
+use camera::{OrbitControl, Drag, AxisView};
+
+mod picking;
+use picking::ChargeDrag;
+
+mod editor;
+use editor::ChargeEditor;
+
+mod scene;
+use scene::{Scenes, Control};
+
+mod lic;
+use lic::LicSlice;
+
+mod particles;
+use particles::Particles;
+
+mod raster;
+mod record;
+use record::Timeline;
+
+mod keyframes;
+
+mod sweep;
+use sweep::{Sweep, Parameter};
+
+mod probe;
+use probe::Probes;
+
+mod units;
+use units::Quantity;
+
+mod forces;
+use forces::ForceReport;
+
+mod null_points;
+use null_points::NullPoints;
 
 #[macro_use] mod util;
 
@@ -26,6 +82,38 @@ type Ui = conrod::Ui<Backend>;
 type UiCell<'a> = conrod::UiCell<'a, Backend>;
 
 fn main() {
+    let assets = find_folder::Search::ParentsThenKids(3, 3)
+        .for_folder("assets").unwrap();
+    let mut scenes = Scenes::builtin();
+    let load_errors = scenes.load_dir(&assets.join("scenes"));
+    // Listed in the sidebar, or on the console when there is no window
+    if std::env::args().any(|arg| arg == "--bench" || arg == "--record") {
+        for e in &load_errors {
+            println!("{}", e);
+        }
+    }
+
+    if std::env::args().any(|arg| arg == "--bench") {
+        bench::run(scenes.iter_mut().map(|s| (&s.name[..], &mut *s.view)).collect());
+        return;
+    }
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--record") {
+        let result = args.get(i + 1)
+            .ok_or("usage: --record <timeline file>".to_string())
+            .and_then(|path| Timeline::load(std::path::Path::new(path)))
+            .and_then(|timeline| {
+                record::record(&timeline, &mut scenes)
+                    .map(|frames| println!("Wrote {} frames to {}", frames, timeline.output.display()))
+            });
+        if let Err(e) = result {
+            println!("Could not record: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let opengl: pw::OpenGL = pw::OpenGL::V3_2;
     let mut window: pw::PistonWindow = pw::WindowSettings::new(
             TITLE,
@@ -38,81 +126,85 @@ fn main() {
 
     window.set_ups(60);
 
+    let font_path = assets.join("fonts/NotoSans/NotoSans-Regular.ttf");
     let mut app: App = App {
         ui: {
             use conrod::Theme;
-            let assets = find_folder::Search::ParentsThenKids(3, 3)
-                .for_folder("assets").unwrap();
-            let font_path = assets.join("fonts/NotoSans/NotoSans-Regular.ttf");
             let theme = Theme::default();
             let glyph_cache = pw::Glyphs::new(
                     &font_path, window.factory.clone()
                 ).unwrap();
             Ui::new(glyph_cache, theme)
         },
-        fields: FieldChoices {
-            one_charge: PointChargesFieldView::new(
-                na::Vector3::new(-GRID_S_2, -GRID_S_2, 75.0),
-                474.0, // greatest field
-                931.0, // pot corresponding to lightest color
-                6158.0, // pot corresponding to darkest color
-                vec![PointCharge::new(8.0, na::Point3::new(GRID_S_2, GRID_S_2, GRID_S_2))]
-            ),
-            two_charges_np: PointChargesFieldView::new(
-                na::Vector3::new(0.0, -GRID_S_2, 75.0),
-                602.4, // greatest field
-                -6495.8, // pot corresponding to lightest color
-                6495.8, // pot corresponding to darkest color
-                vec![
-                    PointCharge::new(8.0, na::Point3::new(5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
-                    PointCharge::new(-8.0, na::Point3::new(-5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
-                ]
-            ),
-            two_charges_same: PointChargesFieldView::new(
-                na::Vector3::new(0.0, -GRID_S_2, 75.0),
-                602.4, // greatest field
-                2343.0, // pot corresponding to lightest color
-                9161.0, // pot corresponding to darkest color
-                vec![
-                    PointCharge::new(8.0, na::Point3::new(5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
-                    PointCharge::new(8.0, na::Point3::new(-5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
-                ]
-            ),
-            capacitor: PointChargesFieldView::new_capacitor(
-                na::Vector3::new(0.0, 5.0, 75.0),
-                1454.5,
-                49524.8
-            ),
-        },
-        selected: FieldChoice::TwoChargesNP,
+        glyphs: pw::Glyphs::new(&font_path, window.factory.clone()).unwrap(),
+        selected: scenes.index_of(DEFAULT_SCENE).unwrap_or(0),
+        scenes: scenes,
+        load_errors: load_errors,
         view: [VIEW_RIGHT - VIEW_W, VIEW_BOTTOM - VIEW_H, VIEW_W, VIEW_H],
         window: [WIDTH, HEIGHT],
         rebuild_queued: false,
         redraw_queued: true, // true for initial render of field
         redraw_echo_queued: false,
+        dynamics: None,
+        dynamics_running: false,
+        show_grid_controls: false,
+        show_depth_controls: false,
+        lic: LicSlice::new(),
+        show_lic: false,
+        lic_export: None,
+        particles: Particles::new(),
+        show_particles: false,
+        sweep: Sweep::new(Parameter::Charge(0), 10.0, -10.0, SWEEP_DURATION),
+        show_sweep_controls: false,
+        probes: Probes::new(),
+        show_forces: false,
+        forces: None,
+        nulls: NullPoints::new(),
+        show_nulls: false,
+        orbit: OrbitControl::new(na::Point3::new(0.0, 0.0, 0.0)),
+        cursor: [0.0, 0.0],
+        charge_drag: None,
+        snap_to_grid: false,
+        editor: ChargeEditor::new(),
+        show_editor: false,
+        ctrl_held: false,
+        camera_path_saved: None,
     };
 
-    app.fields.one_charge.populate_field();
-    app.fields.one_charge.transform_arrows(util::euler_rot_mat4(0.0, PI * 0.08, 0.0));
-    app.fields.two_charges_np.populate_field();
-    app.fields.two_charges_same.populate_field();
-    app.fields.capacitor.populate_field();
+    let target = camera::grid_center(app.active_field());
+    app.orbit.retarget(target);
 
     while let Some(event) = window.next() {
         use pw::ResizeEvent;
         app.ui.handle_event(event.clone());
         match event {
             pw::Event::Render(_args) => {
+                app.lic.upload(&mut window.factory);
                 window.draw_2d(&event, |context, graphics| {
                     app.render(context, graphics);
                 });
             },
-            pw::Event::Update(_args) => {
-                app.update();
+            pw::Event::Update(args) => {
+                app.update(args.dt);
             },
             pw::Event::Input(pw::Input::Press(pw::Button::Keyboard(key))) => {
                 app.keypress(key);
             },
+            pw::Event::Input(pw::Input::Release(pw::Button::Keyboard(key))) => {
+                app.keyrelease(key);
+            },
+            pw::Event::Input(pw::Input::Press(pw::Button::Mouse(button))) => {
+                app.mouse_press(button);
+            },
+            pw::Event::Input(pw::Input::Release(pw::Button::Mouse(button))) => {
+                app.mouse_release(button);
+            },
+            pw::Event::Input(pw::Input::Move(pw::Motion::MouseCursor(x, y))) => {
+                app.mouse_move([x, y]);
+            },
+            pw::Event::Input(pw::Input::Move(pw::Motion::MouseScroll(_, dy))) => {
+                app.scroll(dy);
+            },
             _ => {
                 if let Some(_) = event.resize_args() {
                     app.redraw_queued = true;
@@ -124,25 +216,15 @@ fn main() {
     }
 }
 
-struct FieldChoices {
-    one_charge: PointChargesFieldView,
-    two_charges_np: PointChargesFieldView,
-    two_charges_same: PointChargesFieldView,
-    capacitor: PointChargesFieldView,
-}
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum FieldChoice {
-    OneCharge,
-    TwoChargesNP,
-    TwoChargesSame,
-    Capacitor,
-}
-
 struct App {
     ui: Ui,
-    fields: FieldChoices,
-    selected: FieldChoice,
+    // For labels drawn in the view
+    glyphs: pw::Glyphs,
+    scenes: Scenes,
+    // Why scene and keyframe files could not be loaded, for display
+    load_errors: Vec<String>,
+    // Index of the scene shown
+    selected: usize,
     view: [f64; 4], // [x, y, width, height]
     window: [u32; 2], // [width, height]
     rebuild_queued: bool, // for rebuilding field arrows after changes to, e.g., charge strengths
@@ -150,21 +232,103 @@ struct App {
     // Upon drawing, we draw once more in the next frame (see
     // problem described in message of commit 93fe0b).
     redraw_echo_queued: bool,
+    // The simulation of the charges of the selected field, if one has
+    // been started, and whether it is currently advancing
+    dynamics: Option<Dynamics>,
+    dynamics_running: bool,
+    // Whether the sliders for the grid's extent and spacing, and the
+    // depth cueing settings, are shown
+    show_grid_controls: bool,
+    show_depth_controls: bool,
+    // The LIC texture of a slice through the field of the selected scene,
+    // whether it is shown, and the outcome of its last export
+    lic: LicSlice,
+    show_lic: bool,
+    lic_export: Option<String>,
+    // Tracers drifting along the field lines, and whether they are animated
+    particles: Particles,
+    show_particles: bool,
+    // An animation of a parameter of the charges of the selected scene
+    sweep: Sweep,
+    show_sweep_controls: bool,
+    // Points at which the field is read out
+    probes: Probes,
+    // Whether the forces between the charges are drawn and listed, and the
+    // forces, found again after the charges change
+    show_forces: bool,
+    forces: Option<ForceReport>,
+    // Points of the selected field at which it vanishes, and whether they
+    // are marked
+    nulls: NullPoints,
+    show_nulls: bool,
+    // Mouse control of the camera, and the last known cursor position
+    orbit: OrbitControl,
+    cursor: [f64; 2],
+    // The charge being dragged with the mouse, if any, and whether its
+    // location is rounded to CHARGE_SNAP_STEP
+    charge_drag: Option<ChargeDrag>,
+    snap_to_grid: bool,
+    // The charge list panel, which also selects the charge moved by TFGH,RY
+    editor: ChargeEditor,
+    show_editor: bool,
+    // Whether either Ctrl key is down, for Ctrl+Z and Ctrl+Y
+    ctrl_held: bool,
+    // The outcome of the last save of a camera path, for display
+    camera_path_saved: Option<String>,
 }
 
 impl App {
-    fn update(&mut self) {
+    fn update(&mut self, dt: f64) {
         self.set_widgets();
+        if self.dynamics_running {
+            if let (Some(dynamics), Some(field)) = (self.dynamics.as_mut(), self.scenes.charges_mut(self.selected)) {
+                dynamics.advance(&mut field.charges, dt);
+                field.charges_changed();
+            }
+            self.rebuild_queued = true;
+        }
+        if self.orbit.update(self.scenes.view_mut(self.selected)) {
+            self.redraw_queued = true;
+        }
+        if let Some(field) = self.scenes.charges_mut(self.selected) {
+            if self.sweep.advance(field, dt) {
+                self.editor.reload();
+                self.rebuild_queued = true;
+            }
+        }
+        {
+            let scene = self.scenes.get_mut(self.selected);
+            if let Some(camera) = scene.camera_path.advance(dt) {
+                scene.view.set_camera(camera);
+                self.redraw_queued = true;
+            }
+        }
+        if self.show_particles {
+            self.particles.advance(self.scenes.view_mut(self.selected), dt);
+            self.redraw_queued = true;
+        }
     }
 
     fn idle(&mut self) {
         if self.rebuild_queued {
-            self.active_field().populate_field();
+            self.active_field().rebuild();
             self.active_field().reapply_arrow_transforms();
+            self.lic.invalidate();
+            self.nulls.invalidate();
+            self.forces = None;
             self.rebuild_queued = false;
             // Then, redraw
             self.redraw_queued = true;
         }
+        if self.show_lic && self.lic.update(self.scenes.view_mut(self.selected)) {
+            self.redraw_queued = true;
+        }
+        // The search takes too long to repeat every frame, so it waits for
+        // the charges to come to rest
+        let animating = self.dynamics_running || self.sweep.playing || self.charge_drag.is_some();
+        if self.show_nulls && !animating && self.nulls.update(self.scenes.view_mut(self.selected)) {
+            self.redraw_queued = true;
+        }
     }
 
     fn render(&mut self, c: pw::Context, g: &mut pw::G2d) {
@@ -174,7 +338,32 @@ impl App {
             let mut context = c.clone();
             context.draw_state.scissor = Some(self.get_view_scissor());
             let view = self.view; // must copy b/c self is mutably borrowed in next line
+            // Clear the section on which we will draw
+            pw::Rectangle::new(pw::color::WHITE).draw(view, &context.draw_state, context.transform, g);
+            if self.show_lic {
+                self.lic.draw(context, g, self.scenes.view_mut(self.selected), view);
+            }
             self.active_field().render(context, g, view);
+            if self.show_particles {
+                self.particles.draw(context, g, self.scenes.view_mut(self.selected), view);
+            }
+            if let Some(field) = self.scenes.charges_mut(self.selected) {
+                field.render_charges(context, g, &mut self.glyphs, view, Some(self.editor.selected));
+            }
+            if self.show_nulls {
+                self.nulls.draw(context, g, self.scenes.view_mut(self.selected), view);
+            }
+            if self.show_forces {
+                if let Some(field) = self.scenes.charges_mut(self.selected) {
+                    if self.forces.is_none() {
+                        self.forces = Some(ForceReport::new(field));
+                    }
+                    if let Some(ref report) = self.forces {
+                        report.draw(context, g, field, view);
+                    }
+                }
+            }
+            self.probes.draw(context, g, &mut self.glyphs, self.scenes.view_mut(self.selected), view);
             if self.redraw_queued {
                 self.redraw_echo_queued = true;
                 self.redraw_queued = false;
@@ -199,11 +388,167 @@ impl App {
     }
 
     fn active_field(&mut self) -> &mut FieldView {
-        match self.selected {
-            FieldChoice::OneCharge => &mut self.fields.one_charge,
-            FieldChoice::TwoChargesNP => &mut self.fields.two_charges_np,
-            FieldChoice::TwoChargesSame => &mut self.fields.two_charges_same,
-            FieldChoice::Capacitor => &mut self.fields.capacitor,
+        self.scenes.view_mut(self.selected)
+    }
+
+    // Move the charge selected in the charge editor, which is controlled
+    // by TFGH,RY
+    fn move_controlled_charge(&mut self, delta: na::Vector3<f64>) {
+        let field = match self.scenes.charges_mut(self.selected) {
+            Some(field) => field,
+            None => return,
+        };
+        if self.editor.selected < field.charges.len() {
+            field.move_charge(self.editor.selected, delta);
+            // Each press is undone separately
+            field.seal_history();
+            self.editor.reload();
+            self.rebuild_queued = true;
+        }
+    }
+
+    fn in_view(&self, pos: [f64; 2]) -> bool {
+        pos[0] >= self.view[0] && pos[0] < self.view[0] + self.view[2]
+            && pos[1] >= self.view[1] && pos[1] < self.view[1] + self.view[3]
+    }
+
+    fn mouse_press(&mut self, button: pw::MouseButton) {
+        if !self.in_view(self.cursor) {
+            return;
+        }
+        match button {
+            pw::MouseButton::Left if self.probes.placing => {
+                let (cursor, view) = (self.cursor, self.view);
+                self.probes.place(self.scenes.view_mut(self.selected), cursor, view);
+                self.redraw_queued = true;
+            },
+            pw::MouseButton::Left => {
+                let (cursor, view) = (self.cursor, self.view);
+                let picked = self.scenes.charges_mut(self.selected).and_then(|field| {
+                    picking::pick_charge(field, cursor, view)
+                        .map(|i| ChargeDrag::new(field, i))
+                });
+                match picked {
+                    Some(drag) => {
+                        self.editor.select(drag.index);
+                        self.charge_drag = Some(drag);
+                        // Highlight the newly selected charge
+                        self.redraw_queued = true;
+                    },
+                    None => self.orbit.start_drag(Drag::Orbit),
+                }
+            },
+            pw::MouseButton::Right => self.orbit.start_drag(Drag::Pan),
+            _ => {},
+        }
+    }
+
+    fn mouse_release(&mut self, _button: pw::MouseButton) {
+        self.charge_drag = None;
+        // Ends any drag of a charge or of a slider, which is undone as one
+        if let Some(field) = self.scenes.charges_mut(self.selected) {
+            field.seal_history();
+        }
+        if self.orbit.dragging() {
+            self.orbit.end_drag();
+        }
+    }
+
+    fn mouse_move(&mut self, pos: [f64; 2]) {
+        self.cursor = pos;
+        let view = self.view;
+        if let (Some(drag), Some(field)) = (self.charge_drag.as_ref(), self.scenes.charges_mut(self.selected)) {
+            let loc = drag.location(field, pos, view, self.snap_to_grid);
+            if drag.index < field.charges.len() && loc != field.charges[drag.index].loc {
+                field.set_charge_loc(drag.index, loc);
+                self.editor.reload();
+                self.rebuild_queued = true;
+            }
+        }
+        if self.orbit.cursor_moved(self.scenes.view_mut(self.selected), pos, view) {
+            self.redraw_queued = true;
+        }
+    }
+
+    fn scroll(&mut self, steps: f64) {
+        if self.in_view(self.cursor) {
+            self.orbit.zoom(self.scenes.view_mut(self.selected), steps);
+            self.redraw_queued = true;
+        }
+    }
+
+    // Stop any simulation, returning the charges to where it started
+    fn stop_dynamics(&mut self) {
+        if let (Some(d), Some(field)) = (self.dynamics.take(), self.scenes.charges_mut(self.selected)) {
+            d.reset(field);
+            field.rebuild();
+            field.reapply_arrow_transforms();
+        }
+        self.forces = None;
+        self.dynamics_running = false;
+        self.editor.reload();
+    }
+
+    // End any simulation, leaving the charges where they are
+    fn end_dynamics(&mut self) {
+        self.dynamics = None;
+        self.dynamics_running = false;
+    }
+
+    fn undo(&mut self) {
+        // The simulation's initial state would no longer match the charges
+        self.end_dynamics();
+        if self.scenes.charges_mut(self.selected).map_or(false, |field| field.undo()) {
+            self.editor.reload();
+            self.rebuild_queued = true;
+        }
+    }
+
+    fn redo(&mut self) {
+        self.end_dynamics();
+        if self.scenes.charges_mut(self.selected).map_or(false, |field| field.redo()) {
+            self.editor.reload();
+            self.rebuild_queued = true;
+        }
+    }
+
+    fn reset_view(&mut self) {
+        self.active_field().reset_camera();
+        let target = camera::grid_center(self.active_field());
+        self.orbit.retarget(target);
+        self.redraw_queued = true;
+    }
+
+    // Switch between perspective and orthographic projection. The
+    // orthographic view is made to show as much as the perspective one
+    // does at the depth of the orbit target.
+    fn toggle_projection(&mut self) {
+        let field = self.scenes.view_mut(self.selected);
+        let depth = util::f64_max(self.orbit.target_depth(field), NEAR_PLANE_Z);
+        let projection = field.projection_mut();
+        projection.kind = if projection.is_orthographic() {
+            ProjectionKind::Perspective
+        } else {
+            projection.ortho_half_width = depth * projection.half_fov_tan();
+            ProjectionKind::Orthographic
+        };
+        self.redraw_queued = true;
+    }
+
+    fn snap_view(&mut self, axis_view: AxisView) {
+        self.orbit.snap(self.scenes.view_mut(self.selected), axis_view.rotation());
+        self.redraw_queued = true;
+    }
+
+    // Move the camera so that all of the charges of the field are in view
+    fn frame_charges(&mut self) {
+        let field = match self.scenes.charges_mut(self.selected) {
+            Some(field) => field,
+            None => return,
+        };
+        if let Some((center, radius)) = camera::bounding_sphere(&field.transformed_charge_locs()) {
+            self.orbit.frame(field, center, radius);
+            self.redraw_queued = true;
         }
     }
 
@@ -219,127 +564,109 @@ impl App {
     }
 
     fn keypress(&mut self, key: pw::Key) {
+        match key {
+            pw::Key::LCtrl | pw::Key::RCtrl => {
+                self.ctrl_held = true;
+                return;
+            },
+            pw::Key::Z if self.ctrl_held => {
+                self.undo();
+                return;
+            },
+            pw::Key::Y if self.ctrl_held => {
+                self.redo();
+                return;
+            },
+            _ => {},
+        }
         let mut queue_redraw = true;
         match key {
             pw::Key::Up => {
-                self.active_field().transform_camera(util::euler_rot_mat4(PI * 0.01, 0.0, 0.0));
+                self.active_field().transform_camera(&Pose::euler(PI * 0.01, 0.0, 0.0));
             },
             pw::Key::Down => {
-                self.active_field().transform_camera(util::euler_rot_mat4(-PI * 0.01, 0.0, 0.0));
+                self.active_field().transform_camera(&Pose::euler(-PI * 0.01, 0.0, 0.0));
             },
             pw::Key::Right => {
-                self.active_field().transform_camera(util::euler_rot_mat4(0.0, -PI * 0.01, 0.0));
+                self.active_field().transform_camera(&Pose::euler(0.0, -PI * 0.01, 0.0));
             },
             pw::Key::Left => {
-                self.active_field().transform_camera(util::euler_rot_mat4(0.0, PI * 0.01, 0.0));
+                self.active_field().transform_camera(&Pose::euler(0.0, PI * 0.01, 0.0));
             },
             pw::Key::W => {
-                self.active_field().transform_camera(util::translation_mat4(na::Vector3::new(0.0, 0.0, -1.0)));
+                self.active_field().transform_camera(&Pose::translation(na::Vector3::new(0.0, 0.0, -1.0)));
             },
             pw::Key::S => {
-                self.active_field().transform_camera(util::translation_mat4(na::Vector3::new(0.0, 0.0, 1.0)));
+                self.active_field().transform_camera(&Pose::translation(na::Vector3::new(0.0, 0.0, 1.0)));
             },
             pw::Key::D => {
-                self.active_field().transform_camera(util::translation_mat4(na::Vector3::new(-1.0, 0.0, 0.0)));
+                self.active_field().transform_camera(&Pose::translation(na::Vector3::new(-1.0, 0.0, 0.0)));
             },
             pw::Key::A => {
-                self.active_field().transform_camera(util::translation_mat4(na::Vector3::new(1.0, 0.0, 0.0)));
+                self.active_field().transform_camera(&Pose::translation(na::Vector3::new(1.0, 0.0, 0.0)));
             },
             pw::Key::Q => {
-                self.active_field().transform_camera(util::translation_mat4(na::Vector3::new(0.0, -1.0, 0.0)));
+                self.active_field().transform_camera(&Pose::translation(na::Vector3::new(0.0, -1.0, 0.0)));
             },
             pw::Key::E => {
-                self.active_field().transform_camera(util::translation_mat4(na::Vector3::new(0.0, 1.0, 0.0)));
+                self.active_field().transform_camera(&Pose::translation(na::Vector3::new(0.0, 1.0, 0.0)));
             },
             pw::Key::I => {
-                self.active_field().transform_arrows(util::euler_rot_mat4(-PI * 0.01, 0.0, 0.0));
+                self.active_field().transform_arrows(&Pose::euler(-PI * 0.01, 0.0, 0.0));
             },
             pw::Key::K => {
-                self.active_field().transform_arrows(util::euler_rot_mat4(PI * 0.01, 0.0, 0.0));
+                self.active_field().transform_arrows(&Pose::euler(PI * 0.01, 0.0, 0.0));
             },
             pw::Key::L => {
-                self.active_field().transform_arrows(util::euler_rot_mat4(0.0, -PI * 0.01, 0.0));
+                self.active_field().transform_arrows(&Pose::euler(0.0, -PI * 0.01, 0.0));
             },
             pw::Key::J => {
-                self.active_field().transform_arrows(util::euler_rot_mat4(0.0, PI * 0.01, 0.0));
+                self.active_field().transform_arrows(&Pose::euler(0.0, PI * 0.01, 0.0));
             },
             pw::Key::T => {
-                match self.selected {
-                    FieldChoice::TwoChargesNP => {
-                        self.fields.two_charges_np.charges[0].loc.y -= CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    FieldChoice::TwoChargesSame => {
-                        self.fields.two_charges_same.charges[0].loc.y -= CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    _ => {},
-                }
+                self.move_controlled_charge(na::Vector3::new(0.0, -CHARGE_MVMT_STEP, 0.0));
             },
             pw::Key::G => {
-                match self.selected {
-                    FieldChoice::TwoChargesNP => {
-                        self.fields.two_charges_np.charges[0].loc.y += CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    FieldChoice::TwoChargesSame => {
-                        self.fields.two_charges_same.charges[0].loc.y += CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    _ => {},
-                }
+                self.move_controlled_charge(na::Vector3::new(0.0, CHARGE_MVMT_STEP, 0.0));
             },
             pw::Key::H => {
-                match self.selected {
-                    FieldChoice::TwoChargesNP => {
-                        self.fields.two_charges_np.charges[0].loc.x += CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    FieldChoice::TwoChargesSame => {
-                        self.fields.two_charges_same.charges[0].loc.x += CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    _ => {},
-                }
+                self.move_controlled_charge(na::Vector3::new(CHARGE_MVMT_STEP, 0.0, 0.0));
             },
             pw::Key::F => {
-                match self.selected {
-                    FieldChoice::TwoChargesNP => {
-                        self.fields.two_charges_np.charges[0].loc.x -= CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    FieldChoice::TwoChargesSame => {
-                        self.fields.two_charges_same.charges[0].loc.x -= CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    _ => {},
-                }
+                self.move_controlled_charge(na::Vector3::new(-CHARGE_MVMT_STEP, 0.0, 0.0));
             },
             pw::Key::R => {
-                match self.selected {
-                    FieldChoice::TwoChargesNP => {
-                        self.fields.two_charges_np.charges[0].loc.z -= CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    FieldChoice::TwoChargesSame => {
-                        self.fields.two_charges_same.charges[0].loc.z -= CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    _ => {},
-                }
+                self.move_controlled_charge(na::Vector3::new(0.0, 0.0, -CHARGE_MVMT_STEP));
             },
             pw::Key::Y => {
-                match self.selected {
-                    FieldChoice::TwoChargesNP => {
-                        self.fields.two_charges_np.charges[0].loc.z += CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    FieldChoice::TwoChargesSame => {
-                        self.fields.two_charges_same.charges[0].loc.z += CHARGE_MVMT_STEP;
-                        self.rebuild_queued = true;
-                    },
-                    _ => {},
-                }
+                self.move_controlled_charge(na::Vector3::new(0.0, 0.0, CHARGE_MVMT_STEP));
+            },
+            pw::Key::Home => {
+                self.reset_view();
+            },
+            pw::Key::C => {
+                self.frame_charges();
+            },
+            pw::Key::O => {
+                self.toggle_projection();
+            },
+            pw::Key::D1 => {
+                self.snap_view(AxisView::Front);
+            },
+            pw::Key::D2 => {
+                self.snap_view(AxisView::Back);
+            },
+            pw::Key::D3 => {
+                self.snap_view(AxisView::Top);
+            },
+            pw::Key::D4 => {
+                self.snap_view(AxisView::Bottom);
+            },
+            pw::Key::D5 => {
+                self.snap_view(AxisView::Left);
+            },
+            pw::Key::D6 => {
+                self.snap_view(AxisView::Right);
             },
             _ => {
                 queue_redraw = false;
@@ -350,20 +677,72 @@ impl App {
         }
     }
 
+    fn keyrelease(&mut self, key: pw::Key) {
+        if key == pw::Key::LCtrl || key == pw::Key::RCtrl {
+            self.ctrl_held = false;
+        }
+    }
+
     fn set_widgets(&mut self) {
         let h = self.window[1] as f64;
-        let fields = &mut self.fields;
+        let scenes = &mut self.scenes;
         let view = self.view;
         let mut queue_rebuild = false;
         let mut queue_redraw = false;
         let mut selected_field = self.selected;
+        let dynamics = &mut self.dynamics;
+        let dynamics_running = &mut self.dynamics_running;
+        let show_grid_controls = &mut self.show_grid_controls;
+        let show_depth_controls = &mut self.show_depth_controls;
+        let lic = &mut self.lic;
+        let show_lic = &mut self.show_lic;
+        let lic_export = &mut self.lic_export;
+        let particles = &mut self.particles;
+        let show_particles = &mut self.show_particles;
+        let sweep = &mut self.sweep;
+        let probes = &mut self.probes;
+        let show_forces = &mut self.show_forces;
+        let forces = &mut self.forces;
+        let nulls = &mut self.nulls;
+        let show_nulls = &mut self.show_nulls;
+        let show_sweep_controls = &mut self.show_sweep_controls;
+        let mut reset_view = false;
+        let mut frame_charges = false;
+        let mut axis_view = None;
+        let mut toggle_projection = false;
+        let mut save_camera_path = false;
+        let camera_path_saved = &self.camera_path_saved;
+        let load_errors = &self.load_errors;
+        let snap_to_grid = &mut self.snap_to_grid;
+        let editor = &mut self.editor;
+        let show_editor = &mut self.show_editor;
+        if let Some(field) = scenes.charges_mut(selected_field) {
+            editor.sync(field);
+        }
+        let orientation_text = {
+            let field = scenes.view_mut(selected_field);
+            let (cam_yaw, cam_pitch, cam_roll) = field.camera().yaw_pitch_roll();
+            let (field_yaw, field_pitch, field_roll) = field.arrow_transforms().yaw_pitch_roll();
+            format!("Camera: yaw {:.1}\u{b0}, pitch {:.1}\u{b0}, roll {:.1}\u{b0}\n\
+                     Field: yaw {:.1}\u{b0}, pitch {:.1}\u{b0}, roll {:.1}\u{b0}",
+                    cam_yaw, cam_pitch, cam_roll, field_yaw, field_pitch, field_roll)
+        };
+        // Energy and momentum of the simulated charges, displayed to show
+        // that they are conserved
+        let conserved_text = dynamics.as_ref().and_then(|d| scenes.charges_mut(selected_field).map(|field| {
+            let conserved = d.conserved(&field.charges);
+            let energy = |e| field.units.format(e, Quantity::Energy);
+            format!("t = {:.2}\nEnergy: {} (K = {}, U = {})\nMomentum: ({:.2}, {:.2}, {:.2})",
+                d.time, energy(conserved.energy()), energy(conserved.kinetic), energy(conserved.potential),
+                conserved.momentum.x, conserved.momentum.y, conserved.momentum.z)
+        }));
         self.ui.set_widgets(|ref mut ui: UiCell| {
             use conrod::{color, Widget, Canvas, Text, Slider, Sizeable, Colorable, Positionable, Frameable};
             Canvas::new().flow_down(&[
                     (HEADER, Canvas::new().length(BANNER_HEIGHT).color(color::CHARCOAL).frame(0.0)),
                     (CONTENT, Canvas::new().length(h - BANNER_HEIGHT).flow_right(&[
                         (BODY_LEFT, Canvas::new().color(color::DARK_CHARCOAL).length(CHROME_PAD as f64).frame(0.0)),
-                        (BODY, Canvas::new().color(color::DARK_CHARCOAL).length(view[0] - CHROME_PAD as f64).pad_top(CHROME_PAD as f64).frame(0.0))
+                        (BODY, Canvas::new().color(color::DARK_CHARCOAL).length(view[0] - CHROME_PAD as f64).pad_top(CHROME_PAD as f64).scroll_kids_vertically().frame(0.0))
                     ]).color(color::TRANSPARENT))
                 ]).top_left().color(color::TRANSPARENT).w(view[0]).set(CANVAS, ui);
             Text::new(TITLE)
@@ -376,7 +755,11 @@ impl App {
             description_top("Controls:\n \
                 - WASD,QE to move the camera\n \
                 - arrow keys to look around\n \
-                - IJKL to rotate field").set(INSTRUCTIONS_0, ui);
+                - IJKL to rotate field\n \
+                - drag to orbit, right-drag to pan, scroll to zoom\n \
+                - drag a charge to move it\n \
+                - Ctrl+Z, Ctrl+Y to undo and redo edits of charges\n \
+                - 1-6 to look along the axes, O for orthographic").set(INSTRUCTIONS_0, ui);
             description("The length of an arrow represents the strength of the field, and the shading the potential. \
                 Darker arrows have higher potential.", INSTRUCTIONS_0)
                 .set(INSTRUCTIONS_1, ui);
@@ -385,119 +768,910 @@ impl App {
 
             // Buttons for selecting type of field
             description("Choose field:", INSTRUCTIONS_2).h(15.0).set(CHOOSE_TEXT, ui);
-            field_btn_top("One charge", CHOOSE_TEXT, selected_field == FieldChoice::OneCharge)
-                .react(|| {
-                    selected_field = FieldChoice::OneCharge;
-                    queue_redraw = true;
-                }).set(FIELDBTN_ONE, ui);
-            field_btn("Two identical charges", FIELDBTN_ONE, selected_field == FieldChoice::TwoChargesSame)
-                .react(|| {
-                    selected_field = FieldChoice::TwoChargesSame;
-                    queue_redraw = true;
-                }).set(FIELDBTN_TWO, ui);
-            field_btn("Two opposite charges", FIELDBTN_TWO, selected_field == FieldChoice::TwoChargesNP)
-                .react(|| {
-                    selected_field = FieldChoice::TwoChargesNP;
-                    queue_redraw = true;
-                }).set(FIELDBTN_THREE, ui);
-            field_btn("Capacitor", FIELDBTN_THREE, selected_field == FieldChoice::Capacitor)
-                .react(|| {
-                    selected_field = FieldChoice::Capacitor;
+            let mut above = CHOOSE_TEXT;
+            for i in 0..scenes.len() {
+                let name = &scenes.get(i).name;
+                let btn = if i == 0 {
+                    field_btn_top(name, above, selected_field == i)
+                } else {
+                    field_btn(name, above, selected_field == i)
+                };
+                btn.react(|| {
+                    selected_field = i;
                     queue_redraw = true;
-                }).set(FIELDBTN_CAP, ui);
-
-            // Controls
-            match selected_field {
-                FieldChoice::OneCharge => {
-                },
-                FieldChoice::TwoChargesNP => {
-                    let field = &mut fields.two_charges_np;
-                    description("Set magnitudes of charges:", FIELDBTN_THREE).set(SLIDER_NP_INTRO, ui);
-                    // Label and slider for left charge value
-                    let value0 = field.charges[1].charge;
+                }).set(SCENE_BTN + i, ui);
+                above = SCENE_BTN + i;
+            }
+            if !load_errors.is_empty() {
+                Text::new(&load_errors.join("\n"))
+                    .color(color::WHITE)
+                    .w_of(BODY)
+                    .down_from(above, 10.0)
+                    .align_text_left()
+                    .font_size(14)
+                    .set(SCENE_LOAD_TEXT, ui);
+                above = SCENE_LOAD_TEXT;
+            }
+
+            // Controls of the scene
+            let mut last_control = above;
+            {
+                let scene = scenes.get_mut(selected_field);
+                let field = scene.view.point_charges_mut();
+                if let Some(field) = field {
+                    for (k, control) in scene.controls.iter().enumerate().take(MAX_SCENE_CONTROLS) {
+                        let id = SCENE_CONTROL + k;
+                        match *control {
+                            Control::Note(ref text) => {
+                                description(text, last_control).set(id, ui);
+                            },
+                            // The charge may have been deleted in the editor since
+                            // the scene was loaded
+                            Control::ChargeSlider { index, .. } if index >= field.charges.len() => continue,
+                            Control::ChargeSlider { index, ref label, range, keep_sign } => {
+                                let (label_canvas, slider_canvas) = (SCENE_CONTROL_LC + k, SCENE_CONTROL_SC + k);
+                                let (label_id, slider_id) = (SCENE_CONTROL_L + k, SCENE_CONTROL_S + k);
+                                let value = field.charges[index].charge;
+                                let signum = if keep_sign { value.signum() } else { 1.0 };
+                                slider!(
+                                    ids[id, label_canvas, slider_canvas, label_id, slider_id],
+                                    above = last_control,
+                                    view = view, ui = ui,
+                                    value = value,
+                                    range = [signum * range.0, signum * range.1],
+                                    text = label,
+                                    react = |c: f64| {
+                                        field.set_charge(index, c);
+                                        queue_rebuild = true;
+                                    }
+                                );
+                            },
+                            Control::InvertCharges => {
+                                use conrod::{Button, Labelable};
+                                Button::new().label("Invert charges")
+                                    .w_h(INVERT_CHGS_BTN_W, INVERT_CHGS_BTN_H)
+                                    .down_from(last_control, 5.0)
+                                    .react(|| {
+                                        field.invert_charges();
+                                        queue_rebuild = true;
+                                    })
+                                    .set(id, ui);
+                            },
+                        }
+                        last_control = id;
+                    }
+                }
+            }
+
+            // Simulation of the charges' motion under their mutual forces
+            if let Some(field) = scenes.charges_mut(selected_field) {
+                use conrod::{Button, Labelable};
+                let label = if *dynamics_running { "Pause simulation" } else { "Simulate motion" };
+                Button::new().label(label)
+                    .w_h(DYNAMICS_BTN_W, DYNAMICS_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| {
+                        if dynamics.is_none() {
+                            *dynamics = Some(Dynamics::new(&field.charges, field.units));
+                        }
+                        *dynamics_running = !*dynamics_running;
+                    })
+                    .set(DYNAMICS_BTN, ui);
+                if let Some(ref text) = conserved_text {
+                    Button::new().label("Reset charges")
+                        .w_h(DYNAMICS_BTN_W, DYNAMICS_BTN_H)
+                        .right_from(DYNAMICS_BTN, 5.0)
+                        .react(|| {
+                            if let Some(ref d) = *dynamics {
+                                d.reset(field);
+                                editor.reload();
+                            }
+                            *dynamics = None;
+                            *dynamics_running = false;
+                            queue_rebuild = true;
+                        })
+                        .set(DYNAMICS_RESET_BTN, ui);
+                    description(text, DYNAMICS_BTN).set(DYNAMICS_TEXT, ui);
+                    last_control = DYNAMICS_TEXT;
+                } else {
+                    last_control = DYNAMICS_BTN;
+                }
+            }
+
+            // Animation of a parameter of the charges along a time curve
+            if let Some(field) = scenes.charges_mut(selected_field) {
+                use conrod::{Button, Labelable};
+                let label = if *show_sweep_controls { "Hide sweep" } else { "Sweep a parameter" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| *show_sweep_controls = !*show_sweep_controls)
+                    .set(SWEEP_BTN, ui);
+                let playing = sweep.playing;
+                Button::new().label(if playing { "Stop sweep" } else { "Play sweep" })
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .right_from(SWEEP_BTN, 5.0)
+                    .react(|| if playing { sweep.stop() } else if sweep.parameter.applies_to(field) { sweep.play() })
+                    .set(SWEEP_PLAY_BTN, ui);
+                last_control = SWEEP_BTN;
+                if *show_sweep_controls {
+                    let selected_charge = editor.selected;
+                    Button::new().label(&format!("Sweep {}", sweep.parameter.name()))
+                        .w_h(DEPTH_BTN_W * 2.0 + 5.0, GRID_BTN_H)
+                        .down_from(SWEEP_BTN, 5.0)
+                        .react(|| {
+                            sweep.stop();
+                            sweep.parameter = sweep.parameter.next(selected_charge);
+                            if sweep.parameter.applies_to(field) {
+                                let (from, to) = sweep.parameter.default_range(field);
+                                sweep.from = from;
+                                sweep.to = to;
+                            }
+                        })
+                        .set(SWEEP_PARAM_BTN, ui);
+                    Button::new().label(&format!("Curve: {}", sweep.curve.name()))
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .down_from(SWEEP_PARAM_BTN, 5.0)
+                        .react(|| sweep.curve = sweep.curve.next())
+                        .set(SWEEP_CURVE_BTN, ui);
+                    Button::new().label(&format!("Repeat: {}", sweep.repeat.name()))
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .right_from(SWEEP_CURVE_BTN, 5.0)
+                        .react(|| sweep.repeat = sweep.repeat.next())
+                        .set(SWEEP_REPEAT_BTN, ui);
+                    let (lo, hi) = sweep.parameter.limits(field);
+                    let value = sweep.from;
                     slider!(
-                        ids[SLIDER_NP_0, SLIDER_NP_0_LC, SLIDER_NP_0_SC, SLIDER_NP_0_L, SLIDER_NP_0_S],
-                        above = SLIDER_NP_INTRO,
+                        ids[SWEEP_FROM, SWEEP_FROM_LC, SWEEP_FROM_SC, SWEEP_FROM_L, SWEEP_FROM_S],
+                        above = SWEEP_CURVE_BTN,
                         view = view, ui = ui,
-                        value = value0,
-                        range = [0.0, -10.0],
-                        text = "Left charge: ",
-                        react = |c: f64| {
-                            field.charges[1].charge = c;
-                            queue_rebuild = true;
-                        }
+                        value = value,
+                        range = [lo, hi],
+                        text = "From: ",
+                        react = |v: f64| sweep.from = v
                     );
-                    // Label and slider for right charge value
-                    let value1 = field.charges[0].charge;
+                    let value = sweep.to;
                     slider!(
-                        ids[SLIDER_NP_1, SLIDER_NP_1_LC, SLIDER_NP_1_SC, SLIDER_NP_1_L, SLIDER_NP_1_S],
-                        above = SLIDER_NP_0, view = view,
-                        ui = ui,
-                        value = value1,
-                        range = [0.0, 10.0],
-                        text = "Right charge: ",
-                        react = |c: f64| {
-                            field.charges[0].charge = c;
-                            queue_rebuild = true;
-                        }
+                        ids[SWEEP_TO, SWEEP_TO_LC, SWEEP_TO_SC, SWEEP_TO_L, SWEEP_TO_S],
+                        above = SWEEP_FROM,
+                        view = view, ui = ui,
+                        value = value,
+                        range = [lo, hi],
+                        text = "To: ",
+                        react = |v: f64| sweep.to = v
                     );
-                    description("Use TFGH,RY to move the right-side charge around", SLIDER_NP_1).set(TFGHRY_DESCRIPTION, ui);
-                },
-                FieldChoice::TwoChargesSame => {
-                    let field = &mut fields.two_charges_same;
-                    description("Set magnitudes of charges:", FIELDBTN_THREE).set(SLIDER_SAME_INTRO, ui);
-                    // Label and slider for left charge value
-                    let value0 = field.charges[1].charge;
-                    let signum = value0.signum();
+                    let value = sweep.duration;
                     slider!(
-                        ids[SLIDER_SAME_0, SLIDER_SAME_0_LC, SLIDER_SAME_0_SC, SLIDER_SAME_0_L, SLIDER_SAME_0_S],
-                        above = SLIDER_SAME_INTRO,
+                        ids[SWEEP_DURATION_SLIDER, SWEEP_DURATION_LC, SWEEP_DURATION_SC, SWEEP_DURATION_L, SWEEP_DURATION_S],
+                        above = SWEEP_TO,
                         view = view, ui = ui,
-                        value = value0,
-                        range = [signum * 0.1, signum * 10.0],
-                        text = "Left charge: ",
-                        react = |c: f64| {
-                            field.charges[1].charge = c;
+                        value = value,
+                        range = [0.5, MAX_SWEEP_DURATION],
+                        text = "Seconds: ",
+                        react = |v: f64| sweep.duration = v
+                    );
+                    last_control = SWEEP_DURATION_SLIDER;
+                }
+            }
+
+            // Extent and density of the grid of arrows
+            {
+                use conrod::{Button, Labelable};
+                let label = if *show_grid_controls { "Hide grid settings" } else { "Grid settings" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| *show_grid_controls = !*show_grid_controls)
+                    .set(GRID_BTN, ui);
+                if *show_grid_controls {
+                    if let Some(field) = scenes.charges_mut(selected_field) {
+                        let value = field.extent(0);
+                        slider!(
+                            ids[GRID_X, GRID_X_LC, GRID_X_SC, GRID_X_L, GRID_X_S],
+                            above = GRID_BTN,
+                            view = view, ui = ui,
+                            value = value,
+                            range = [GRID_MIN_EXTENT, GRID_MAX_EXTENT],
+                            text = "Width (x): ",
+                            react = |w: f64| {
+                                field.set_extent(0, w);
+                                queue_rebuild = true;
+                            }
+                        );
+                        let value = field.extent(1);
+                        slider!(
+                            ids[GRID_Y, GRID_Y_LC, GRID_Y_SC, GRID_Y_L, GRID_Y_S],
+                            above = GRID_X,
+                            view = view, ui = ui,
+                            value = value,
+                            range = [GRID_MIN_EXTENT, GRID_MAX_EXTENT],
+                            text = "Height (y): ",
+                            react = |h: f64| {
+                                field.set_extent(1, h);
+                                queue_rebuild = true;
+                            }
+                        );
+                        let value = field.extent(2);
+                        slider!(
+                            ids[GRID_Z, GRID_Z_LC, GRID_Z_SC, GRID_Z_L, GRID_Z_S],
+                            above = GRID_Y,
+                            view = view, ui = ui,
+                            value = value,
+                            range = [GRID_MIN_EXTENT, GRID_MAX_EXTENT],
+                            text = "Depth (z): ",
+                            react = |d: f64| {
+                                field.set_extent(2, d);
+                                queue_rebuild = true;
+                            }
+                        );
+                        let value = field.grid_spacing();
+                        slider!(
+                            ids[GRID_SPACING, GRID_SPACING_LC, GRID_SPACING_SC, GRID_SPACING_L, GRID_SPACING_S],
+                            above = GRID_Z,
+                            view = view, ui = ui,
+                            value = value,
+                            range = [GRID_MIN_SPACING, GRID_MAX_SPACING],
+                            text = "Arrow spacing: ",
+                            react = |s: f64| {
+                                field.set_grid_spacing(s);
+                                queue_rebuild = true;
+                            }
+                        );
+                        let label = if field.adaptive_grid { "Adaptive grid: on" } else { "Adaptive grid: off" };
+                        Button::new().label(label)
+                            .w_h(GRID_BTN_W, GRID_BTN_H)
+                            .down_from(GRID_SPACING, 5.0)
+                            .react(|| {
+                                field.adaptive_grid = !field.adaptive_grid;
+                                queue_rebuild = true;
+                            })
+                            .set(GRID_ADAPTIVE_BTN, ui);
+                        last_control = GRID_ADAPTIVE_BTN;
+                        if field.adaptive_grid {
+                            let value = field.arrow_budget as f64;
+                            slider!(
+                                ids[GRID_BUDGET, GRID_BUDGET_LC, GRID_BUDGET_SC, GRID_BUDGET_L, GRID_BUDGET_S],
+                                above = GRID_ADAPTIVE_BTN,
+                                view = view, ui = ui,
+                                value = value,
+                                range = [MIN_ARROW_BUDGET as f64, MAX_ARROW_BUDGET as f64],
+                                text = "Arrow budget: ",
+                                react = |b: f64| {
+                                    field.arrow_budget = b as usize;
+                                    queue_rebuild = true;
+                                }
+                            );
+                            last_control = GRID_BUDGET;
+                        }
+                    }
+                }
+            }
+
+            // Charge list, for editing the charges of any field
+            {
+                use conrod::{Button, TextBox, Labelable};
+                let label = if *show_editor { "Close editor" } else { "Edit charges" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| *show_editor = !*show_editor)
+                    .set(EDITOR_BTN, ui);
+                last_control = EDITOR_BTN;
+                if *show_editor {
+                    if let Some(field) = scenes.charges_mut(selected_field) {
+                        let pages = editor.page_count(field);
+                        let mut select_row = None;
+                        let mut toggle_row = None;
+
+                        // Page through the list
+                        Button::new().label("<")
+                            .w_h(EDITOR_SMALL_BTN_W, GRID_BTN_H)
+                            .down_from(EDITOR_BTN, 5.0)
+                            .react(|| if editor.page > 0 { editor.page -= 1 })
+                            .set(EDITOR_PREV_BTN, ui);
+                        Button::new().label(">")
+                            .w_h(EDITOR_SMALL_BTN_W, GRID_BTN_H)
+                            .right_from(EDITOR_PREV_BTN, 5.0)
+                            .react(|| if editor.page + 1 < pages { editor.page += 1 })
+                            .set(EDITOR_NEXT_BTN, ui);
+                        Text::new(&format!("Page {} of {} ({} charges)", editor.page + 1, pages, field.charges.len()))
+                            .color(color::WHITE)
+                            .right_from(EDITOR_NEXT_BTN, 10.0)
+                            .font_size(14)
+                            .set(EDITOR_PAGE_TEXT, ui);
+
+                        // One row per charge on the page
+                        let mut above = EDITOR_PREV_BTN;
+                        let first = editor.page * EDITOR_ROWS;
+                        for row in 0..EDITOR_ROWS {
+                            let i = first + row;
+                            if i >= field.charges.len() {
+                                break;
+                            }
+                            field_btn(&editor::describe(i, &field.charges[i]), above, i == editor.selected)
+                                .w(view[0] - EDITOR_SMALL_BTN_W * 2.0 - 20.0)
+                                .align_left_of(EDITOR_PREV_BTN)
+                                .react(|| select_row = Some(i))
+                                .set(EDITOR_ROW + row, ui);
+                            Button::new().label(if field.charges[i].visible { "Hide" } else { "Show" })
+                                .w_h(EDITOR_SMALL_BTN_W * 2.0, 18.0)
+                                .right_from(EDITOR_ROW + row, 5.0)
+                                .react(|| toggle_row = Some(i))
+                                .set(EDITOR_VISIBLE_BTN + row, ui);
+                            above = EDITOR_ROW + row;
+                        }
+                        if let Some(i) = select_row {
+                            editor.select(i);
+                            queue_redraw = true;
+                        }
+                        if let Some(i) = toggle_row {
+                            editor.toggle_visible(field, i);
                             queue_rebuild = true;
                         }
-                    );
-                    // Label and slider for right charge value
-                    let value1 = field.charges[0].charge;
-                    slider!(
-                        ids[SLIDER_SAME_1, SLIDER_SAME_1_LC, SLIDER_SAME_1_SC, SLIDER_SAME_1_L, SLIDER_SAME_1_S],
-                        above = SLIDER_SAME_0, view = view,
-                        ui = ui,
-                        value = value1,
-                        range = [signum * 0.1, signum * 10.0],
-                        text = "Right charge: ",
-                        react = |c: f64| {
-                            field.charges[0].charge = c;
+
+                        // Exact values of the selected charge; applied on Enter
+                        if !field.charges.is_empty() {
+                            Text::new("x, y, z and charge (press Enter to apply):")
+                                .color(color::WHITE)
+                                .down_from(above, 10.0)
+                                .align_left_of(EDITOR_PREV_BTN)
+                                .font_size(14)
+                                .set(EDITOR_VALUES_TEXT, ui);
+                            let w = (view[0] - 40.0) / 4.0;
+                            let mut apply = None;
+                            for which in 0..4 {
+                                let text_box = TextBox::new(&mut editor.text[which])
+                                    .w_h(w, EDITOR_TEXTBOX_H)
+                                    .font_size(14)
+                                    .react(|_: &mut String| apply = Some(which));
+                                if which == 0 {
+                                    text_box.down_from(EDITOR_VALUES_TEXT, 5.0).set(EDITOR_VALUE_BOX, ui);
+                                } else {
+                                    text_box.right_from(EDITOR_VALUE_BOX + (which - 1), 5.0).set(EDITOR_VALUE_BOX + which, ui);
+                                }
+                            }
+                            if let Some(which) = apply {
+                                if editor.apply(field, which) {
+                                    queue_rebuild = true;
+                                }
+                            }
+                            above = EDITOR_VALUE_BOX;
+                        }
+
+                        // Adding and removing charges
+                        let mut action = None;
+                        Button::new().label("Add")
+                            .w_h(EDITOR_ACTION_BTN_W, GRID_BTN_H)
+                            .down_from(above, 5.0)
+                            .align_left_of(EDITOR_PREV_BTN)
+                            .react(|| action = Some(0))
+                            .set(EDITOR_ADD_BTN, ui);
+                        Button::new().label("Delete")
+                            .w_h(EDITOR_ACTION_BTN_W, GRID_BTN_H)
+                            .right_from(EDITOR_ADD_BTN, 5.0)
+                            .react(|| action = Some(1))
+                            .set(EDITOR_REMOVE_BTN, ui);
+                        Button::new().label("Duplicate")
+                            .w_h(EDITOR_ACTION_BTN_W, GRID_BTN_H)
+                            .right_from(EDITOR_REMOVE_BTN, 5.0)
+                            .react(|| action = Some(2))
+                            .set(EDITOR_DUPLICATE_BTN, ui);
+                        Button::new().label("Mirror")
+                            .w_h(EDITOR_ACTION_BTN_W, GRID_BTN_H)
+                            .right_from(EDITOR_DUPLICATE_BTN, 5.0)
+                            .react(|| action = Some(3))
+                            .set(EDITOR_MIRROR_BTN, ui);
+                        match action {
+                            Some(0) => editor.add(field),
+                            Some(1) => editor.remove(field),
+                            Some(2) => editor.duplicate(field),
+                            Some(3) => editor.mirror(field),
+                            _ => {},
+                        }
+                        if action.is_some() {
                             queue_rebuild = true;
                         }
+                        last_control = EDITOR_ADD_BTN;
+                    }
+                }
+            }
+
+            // Probes reading out the field at points
+            {
+                use conrod::{Button, TextBox, Labelable};
+                let label = if probes.placing { "Click in the view..." } else { "Place probe" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| probes.placing = !probes.placing)
+                    .set(PROBE_BTN, ui);
+                Button::new().label("Clear probes")
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .right_from(PROBE_BTN, 5.0)
+                    .react(|| {
+                        probes.clear();
+                        queue_redraw = true;
+                    })
+                    .set(PROBE_CLEAR_BTN, ui);
+                Text::new("Or probe at x, y, z (press Enter to add):")
+                    .color(color::WHITE)
+                    .down_from(PROBE_BTN, 10.0)
+                    .font_size(14)
+                    .set(PROBE_COORDS_TEXT, ui);
+                let w = (view[0] - 30.0) / 3.0;
+                let mut add = false;
+                for which in 0..3 {
+                    let text_box = TextBox::new(&mut probes.text[which])
+                        .w_h(w, EDITOR_TEXTBOX_H)
+                        .font_size(14)
+                        .react(|_: &mut String| add = true);
+                    if which == 0 {
+                        text_box.down_from(PROBE_COORDS_TEXT, 5.0).set(PROBE_COORD_BOX, ui);
+                    } else {
+                        text_box.right_from(PROBE_COORD_BOX + (which - 1), 5.0).set(PROBE_COORD_BOX + which, ui);
+                    }
+                }
+                if add {
+                    match probes.add_typed() {
+                        Ok(()) => {
+                            probes.error = None;
+                            queue_redraw = true;
+                        },
+                        Err(e) => probes.error = Some(format!("Could not add probe: {}", e)),
+                    }
+                }
+                let mut above = PROBE_COORD_BOX;
+                if let Some(ref error) = probes.error {
+                    Text::new(error)
+                        .color(color::WHITE)
+                        .w_of(BODY)
+                        .down_from(above, 10.0)
+                        .align_text_left()
+                        .font_size(14)
+                        .set(PROBE_ERROR_TEXT, ui);
+                    above = PROBE_ERROR_TEXT;
+                }
+                let field = scenes.view_mut(selected_field);
+                Text::new(&format!("Units: {}", field.units().name()))
+                    .color(color::WHITE)
+                    .w_of(BODY)
+                    .down_from(above, 10.0)
+                    .align_text_left()
+                    .font_size(14)
+                    .set(PROBE_UNITS_TEXT, ui);
+                last_control = PROBE_UNITS_TEXT;
+
+                // Live readouts, recomputed every update so that they
+                // follow moving charges
+                let mut remove = None;
+                for i in 0..probes.points.len() {
+                    Text::new(&probes.readout(field, i))
+                        .color(color::WHITE)
+                        .w_of(BODY)
+                        .down_from(last_control, 10.0)
+                        .align_text_left()
+                        .font_size(14)
+                        .set(PROBE_TEXT + i, ui);
+                    Button::new().label("Remove")
+                        .w_h(EDITOR_ACTION_BTN_W, 18.0)
+                        .down_from(PROBE_TEXT + i, 5.0)
+                        .react(|| remove = Some(i))
+                        .set(PROBE_REMOVE_BTN + i, ui);
+                    last_control = PROBE_REMOVE_BTN + i;
+                }
+                if let Some(i) = remove {
+                    probes.remove(i);
+                    queue_redraw = true;
+                }
+            }
+
+            // Potential energy of the charges and the forces between them,
+            // found again after the charges change
+            if let Some(field) = scenes.charges_mut(selected_field) {
+                use conrod::{Button, Labelable};
+                let label = if *show_forces { "Hide forces" } else { "Show forces" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| {
+                        *show_forces = !*show_forces;
+                        queue_redraw = true;
+                    })
+                    .set(FORCES_BTN, ui);
+                last_control = FORCES_BTN;
+                if *show_forces {
+                    if forces.is_none() {
+                        *forces = Some(ForceReport::new(field));
+                    }
+                    // Up to an update behind edits made above, so its own
+                    // count of charges is listed
+                    let report = forces.as_ref().unwrap();
+                    let count = report.forces.len();
+                    Text::new(&report.summary(field))
+                        .color(color::WHITE)
+                        .w_of(BODY)
+                        .down_from(FORCES_BTN, 10.0)
+                        .align_text_left()
+                        .font_size(14)
+                        .set(FORCES_SUMMARY_TEXT, ui);
+                    last_control = FORCES_SUMMARY_TEXT;
+                    for i in 0..count.min(MAX_FORCE_READOUTS) {
+                        Text::new(&report.readout(field, i))
+                            .color(color::WHITE)
+                            .w_of(BODY)
+                            .down_from(last_control, 10.0)
+                            .align_text_left()
+                            .font_size(14)
+                            .set(FORCES_TEXT + i, ui);
+                        last_control = FORCES_TEXT + i;
+                    }
+                    if count > MAX_FORCE_READOUTS {
+                        Text::new(&format!("(and {} more charges)", count - MAX_FORCE_READOUTS))
+                            .color(color::WHITE)
+                            .down_from(last_control, 10.0)
+                            .font_size(14)
+                            .set(FORCES_MORE_TEXT, ui);
+                        last_control = FORCES_MORE_TEXT;
+                    }
+                }
+            }
+
+            // Points at which the field vanishes, found in `idle`
+            {
+                use conrod::{Button, Labelable};
+                let label = if *show_nulls { "Hide null points" } else { "Find null points" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| {
+                        *show_nulls = !*show_nulls;
+                        queue_redraw = true;
+                    })
+                    .set(NULL_BTN, ui);
+                last_control = NULL_BTN;
+                if *show_nulls {
+                    let count = nulls.points().len();
+                    let summary = match count {
+                        0 => "No null points in the grid".to_string(),
+                        1 => "1 null point in the grid".to_string(),
+                        n => format!("{} null points in the grid", n),
+                    };
+                    Text::new(&summary)
+                        .color(color::WHITE)
+                        .down_from(NULL_BTN, 10.0)
+                        .font_size(14)
+                        .set(NULL_SUMMARY_TEXT, ui);
+                    last_control = NULL_SUMMARY_TEXT;
+                    for i in 0..count.min(MAX_NULL_READOUTS) {
+                        Text::new(&nulls.readout(i))
+                            .color(color::WHITE)
+                            .w_of(BODY)
+                            .down_from(last_control, 10.0)
+                            .align_text_left()
+                            .font_size(14)
+                            .set(NULL_TEXT + i, ui);
+                        last_control = NULL_TEXT + i;
+                    }
+                }
+            }
+
+            // Camera
+            {
+                use conrod::{Button, Labelable};
+                Button::new().label("Reset view")
+                    .w_h(VIEW_BTN_W, VIEW_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| reset_view = true)
+                    .set(RESET_VIEW_BTN, ui);
+                Button::new().label("Frame charges")
+                    .w_h(VIEW_BTN_W, VIEW_BTN_H)
+                    .right_from(RESET_VIEW_BTN, 5.0)
+                    .react(|| frame_charges = true)
+                    .set(FRAME_CHARGES_BTN, ui);
+                Button::new().label("Front")
+                    .w_h(AXIS_BTN_W, VIEW_BTN_H)
+                    .down_from(RESET_VIEW_BTN, 5.0)
+                    .react(|| axis_view = Some(AxisView::Front))
+                    .set(VIEW_FRONT_BTN, ui);
+                Button::new().label("Top")
+                    .w_h(AXIS_BTN_W, VIEW_BTN_H)
+                    .right_from(VIEW_FRONT_BTN, 5.0)
+                    .react(|| axis_view = Some(AxisView::Top))
+                    .set(VIEW_TOP_BTN, ui);
+                Button::new().label("Side")
+                    .w_h(AXIS_BTN_W, VIEW_BTN_H)
+                    .right_from(VIEW_TOP_BTN, 5.0)
+                    .react(|| axis_view = Some(AxisView::Right))
+                    .set(VIEW_SIDE_BTN, ui);
+                description(&orientation_text, VIEW_FRONT_BTN).set(ORIENTATION_TEXT, ui);
+                let field = scenes.view_mut(selected_field);
+                let label = if field.projection().is_orthographic() {
+                    "Orthographic"
+                } else {
+                    "Perspective"
+                };
+                Button::new().label(label)
+                    .w_h(VIEW_BTN_W, VIEW_BTN_H)
+                    .down_from(ORIENTATION_TEXT, 5.0)
+                    .react(|| toggle_projection = true)
+                    .set(PROJECTION_BTN, ui);
+                let label = if *snap_to_grid { "Snap charges: on" } else { "Snap charges: off" };
+                Button::new().label(label)
+                    .w_h(VIEW_BTN_W, VIEW_BTN_H)
+                    .right_from(PROJECTION_BTN, 5.0)
+                    .react(|| *snap_to_grid = !*snap_to_grid)
+                    .set(SNAP_BTN, ui);
+                if !field.projection().is_orthographic() {
+                    let value = field.projection().fov.to_degrees();
+                    slider!(
+                        ids[FOV, FOV_LC, FOV_SC, FOV_L, FOV_S],
+                        above = PROJECTION_BTN,
+                        view = view, ui = ui,
+                        value = value,
+                        range = [MIN_FOV_DEGREES, MAX_FOV_DEGREES],
+                        text = "Field of view: ",
+                        react = |fov: f64| {
+                            field.projection_mut().fov = fov.to_radians();
+                            queue_redraw = true;
+                        }
                     );
-                    use conrod::{Button, Labelable};
-                    Button::new().label("Invert charges")
-                        .w_h(INVERT_CHGS_BTN_W, INVERT_CHGS_BTN_H)
-                        .down_from(SLIDER_SAME_1, 5.0)
-                        .react(|| {
-                            field.charges[0].charge *= -1.0;
-                            field.charges[1].charge *= -1.0;
+                    last_control = FOV;
+                } else {
+                    last_control = PROJECTION_BTN;
+                }
+            }
 
-                            // Now negate and swap least_pot and greatest_pot, because
-                            // potential value at any given point is negated by negating
-                            // the charges. We maximize contrast by being precise with this
-                            // to make it easier to see potential gradients.
-                            let least_pot = field.least_pot;
-                            field.least_pot = -field.greatest_pot;
-                            field.greatest_pot = -least_pot;
+            // Camera keyframes for fly-throughs
+            {
+                use conrod::{Button, Labelable};
+                let scene = scenes.get_mut(selected_field);
+                let camera = scene.view.camera();
+                let path = &mut scene.camera_path;
+                let status = if path.playing {
+                    format!("Camera path: playing, {:.1} s of {:.1} s", path.time, path.duration())
+                } else {
+                    format!("Camera path: {} keyframes, {:.1} s", path.len(), path.duration())
+                };
+                description(&status, last_control).set(KEYFRAME_TEXT, ui);
+                Button::new().label("Add keyframe")
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(KEYFRAME_TEXT, 5.0)
+                    .react(|| path.record(camera))
+                    .set(KEYFRAME_ADD_BTN, ui);
+                Button::new().label("Remove last")
+                    .w_h(KEYFRAME_BTN_W, GRID_BTN_H)
+                    .right_from(KEYFRAME_ADD_BTN, 5.0)
+                    .react(|| path.remove_last())
+                    .set(KEYFRAME_REMOVE_BTN, ui);
+                Button::new().label("Clear")
+                    .w_h(KEYFRAME_BTN_W, GRID_BTN_H)
+                    .right_from(KEYFRAME_REMOVE_BTN, 5.0)
+                    .react(|| path.clear())
+                    .set(KEYFRAME_CLEAR_BTN, ui);
+                let playing = path.playing;
+                Button::new().label(if playing { "Stop" } else { "Play" })
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(KEYFRAME_ADD_BTN, 5.0)
+                    .react(|| if playing { path.stop() } else { path.play() })
+                    .set(KEYFRAME_PLAY_BTN, ui);
+                let looping = path.looping;
+                Button::new().label(if looping { "Loop: on" } else { "Loop: off" })
+                    .w_h(KEYFRAME_BTN_W, GRID_BTN_H)
+                    .right_from(KEYFRAME_PLAY_BTN, 5.0)
+                    .react(|| path.looping = !looping)
+                    .set(KEYFRAME_LOOP_BTN, ui);
+                Button::new().label("Save")
+                    .w_h(KEYFRAME_BTN_W, GRID_BTN_H)
+                    .right_from(KEYFRAME_LOOP_BTN, 5.0)
+                    .react(|| save_camera_path = true)
+                    .set(KEYFRAME_SAVE_BTN, ui);
+                last_control = KEYFRAME_PLAY_BTN;
+                if let Some(ref saved) = *camera_path_saved {
+                    Text::new(saved)
+                        .color(color::WHITE)
+                        .w_of(BODY)
+                        .down_from(KEYFRAME_PLAY_BTN, 10.0)
+                        .align_text_left()
+                        .font_size(14)
+                        .set(KEYFRAME_SAVE_TEXT, ui);
+                    last_control = KEYFRAME_SAVE_TEXT;
+                }
+            }
 
-                            queue_rebuild = true;
+            // Arrow style, depth sorting, depth cueing and fog
+            {
+                use conrod::{Button, Labelable};
+                let label = if *show_depth_controls { "Hide depth cues" } else { "Depth cues" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| *show_depth_controls = !*show_depth_controls)
+                    .set(DEPTH_BTN, ui);
+                {
+                    let field = scenes.view_mut(selected_field);
+                    let glyphs = field.arrow_style() == ArrowStyle::Glyphs;
+                    Button::new().label(if glyphs { "Arrows: 3D" } else { "Arrows: lines" })
+                        .w_h(GRID_BTN_W, GRID_BTN_H)
+                        .right_from(DEPTH_BTN, 5.0)
+                        .react(|| {
+                            field.set_arrow_style(if glyphs { ArrowStyle::Lines } else { ArrowStyle::Glyphs });
+                            queue_redraw = true;
+                        })
+                        .set(ARROW_STYLE_BTN, ui);
+                }
+                if *show_depth_controls {
+                    let cue = scenes.view_mut(selected_field).depth_cue_mut();
+                    let on_off = |on: bool| if on { "on" } else { "off" };
+                    Button::new().label(&format!("Depth sort: {}", on_off(cue.sort)))
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .down_from(DEPTH_BTN, 5.0)
+                        .react(|| {
+                            cue.sort = !cue.sort;
+                            queue_redraw = true;
+                        })
+                        .set(DEPTH_SORT_BTN, ui);
+                    Button::new().label(&format!("Width by depth: {}", on_off(cue.scale_width)))
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .right_from(DEPTH_SORT_BTN, 5.0)
+                        .react(|| {
+                            cue.scale_width = !cue.scale_width;
+                            queue_redraw = true;
+                        })
+                        .set(DEPTH_WIDTH_BTN, ui);
+                    Button::new().label(&format!("Fade by depth: {}", on_off(cue.fade)))
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .down_from(DEPTH_SORT_BTN, 5.0)
+                        .react(|| {
+                            cue.fade = !cue.fade;
+                            queue_redraw = true;
                         })
-                        .set(INVERT_CHARGES_BTN, ui);
-                    description("Use TFGH,RY to move the right-side charge around", INVERT_CHARGES_BTN).set(TFGHRY_DESCRIPTION, ui);
-                },
-                FieldChoice::Capacitor => {
-                },
+                        .set(DEPTH_FADE_BTN, ui);
+                    Button::new().label(&format!("Fog: {}", on_off(cue.fog)))
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .right_from(DEPTH_FADE_BTN, 5.0)
+                        .react(|| {
+                            cue.fog = !cue.fog;
+                            queue_redraw = true;
+                        })
+                        .set(DEPTH_FOG_BTN, ui);
+                    if cue.fog {
+                        let value = cue.fog_start;
+                        slider!(
+                            ids[FOG_START, FOG_START_LC, FOG_START_SC, FOG_START_L, FOG_START_S],
+                            above = DEPTH_FADE_BTN,
+                            view = view, ui = ui,
+                            value = value,
+                            range = [NEAR_PLANE_Z, MAX_FOG_DISTANCE],
+                            text = "Fog start: ",
+                            react = |d: f64| {
+                                cue.fog_start = d;
+                                cue.fog_end = util::f64_max(cue.fog_end, d);
+                                queue_redraw = true;
+                            }
+                        );
+                        let value = cue.fog_end;
+                        slider!(
+                            ids[FOG_END, FOG_END_LC, FOG_END_SC, FOG_END_L, FOG_END_S],
+                            above = FOG_START,
+                            view = view, ui = ui,
+                            value = value,
+                            range = [NEAR_PLANE_Z, MAX_FOG_DISTANCE],
+                            text = "Fog end: ",
+                            react = |d: f64| {
+                                cue.fog_end = d;
+                                cue.fog_start = util::f64_min(cue.fog_start, d);
+                                queue_redraw = true;
+                            }
+                        );
+                    }
+                }
+                last_control = if !*show_depth_controls {
+                    DEPTH_BTN
+                } else if scenes.view_mut(selected_field).depth_cue().fog {
+                    FOG_END
+                } else {
+                    DEPTH_FADE_BTN
+                };
+            }
+
+            // LIC texture of a slice through the field
+            {
+                use conrod::{Button, Labelable};
+                let label = if *show_lic { "Hide flow texture" } else { "Flow texture (LIC)" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| {
+                        *show_lic = !*show_lic;
+                        queue_redraw = true;
+                    })
+                    .set(LIC_BTN, ui);
+                if *show_lic {
+                    Button::new().label(&format!("Slice: {}", lic.axes.name()))
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .down_from(LIC_BTN, 5.0)
+                        .react(|| {
+                            lic.axes = lic.axes.next();
+                            lic.invalidate();
+                            queue_redraw = true;
+                        })
+                        .set(LIC_AXES_BTN, ui);
+                    let scene_name = scenes.get(selected_field).slug();
+                    Button::new().label("Export PNG")
+                        .w_h(DEPTH_BTN_W, GRID_BTN_H)
+                        .right_from(LIC_AXES_BTN, 5.0)
+                        .react(|| {
+                            let path = format!("lic-{}.png", scene_name);
+                            *lic_export = Some(match lic.save_png(std::path::Path::new(&path)) {
+                                Ok(()) => format!("Saved {}", path),
+                                Err(e) => format!("Could not save {}: {}", path, e),
+                            });
+                        })
+                        .set(LIC_EXPORT_BTN, ui);
+                    let value = lic.offset;
+                    slider!(
+                        ids[LIC_OFFSET, LIC_OFFSET_LC, LIC_OFFSET_SC, LIC_OFFSET_L, LIC_OFFSET_S],
+                        above = LIC_AXES_BTN,
+                        view = view, ui = ui,
+                        value = value,
+                        range = [0.0, 1.0],
+                        text = "Slice position: ",
+                        react = |o: f64| {
+                            lic.offset = o;
+                            lic.invalidate();
+                            queue_redraw = true;
+                        }
+                    );
+                    last_control = LIC_OFFSET;
+                    if let Some(ref status) = *lic_export {
+                        Text::new(status)
+                            .color(color::WHITE)
+                            .w_of(BODY)
+                            .down_from(LIC_OFFSET, 10.0)
+                            .align_text_left()
+                            .font_size(14)
+                            .set(LIC_EXPORT_TEXT, ui);
+                        last_control = LIC_EXPORT_TEXT;
+                    }
+                } else {
+                    last_control = LIC_BTN;
+                }
+            }
+
+            // Tracer particles flowing along the field
+            {
+                use conrod::{Button, Labelable};
+                let label = if *show_particles { "Stop flow particles" } else { "Flow particles" };
+                Button::new().label(label)
+                    .w_h(GRID_BTN_W, GRID_BTN_H)
+                    .down_from(last_control, 25.0)
+                    .react(|| {
+                        *show_particles = !*show_particles;
+                        queue_redraw = true;
+                    })
+                    .set(PARTICLES_BTN, ui);
+                if *show_particles {
+                    let value = particles.count as f64;
+                    slider!(
+                        ids[PARTICLE_COUNT_SLIDER, PARTICLE_COUNT_LC, PARTICLE_COUNT_SC, PARTICLE_COUNT_L, PARTICLE_COUNT_S],
+                        above = PARTICLES_BTN,
+                        view = view, ui = ui,
+                        value = value,
+                        range = [100.0, MAX_PARTICLE_COUNT as f64],
+                        text = "Particles: ",
+                        react = |n: f64| particles.count = n as usize
+                    );
+                    let value = particles.speed;
+                    slider!(
+                        ids[PARTICLE_SPEED_SLIDER, PARTICLE_SPEED_LC, PARTICLE_SPEED_SC, PARTICLE_SPEED_L, PARTICLE_SPEED_S],
+                        above = PARTICLE_COUNT_SLIDER,
+                        view = view, ui = ui,
+                        value = value,
+                        range = [0.1, MAX_PARTICLE_SPEED],
+                        text = "Speed (spacings/s): ",
+                        react = |v: f64| particles.speed = v
+                    );
+                }
             }
         });
         if queue_redraw {
@@ -506,7 +1680,40 @@ impl App {
         if queue_rebuild {
             self.rebuild_queued = true;
         }
-        self.selected = selected_field;
+        if save_camera_path {
+            self.camera_path_saved = Some(match self.scenes.save_camera_path(self.selected) {
+                Ok(path) => format!("Saved camera path to {}", path.display()),
+                Err(e) => format!("Could not save camera path: {}", e),
+            });
+        }
+        if selected_field != self.selected {
+            // A simulation only applies to the field it was started on
+            self.stop_dynamics();
+            self.scenes.get_mut(self.selected).camera_path.stop();
+            self.sweep.stop();
+            self.probes.clear();
+            self.camera_path_saved = None;
+            self.selected = selected_field;
+            let target = camera::grid_center(self.active_field());
+            self.orbit.retarget(target);
+            self.editor.select(0);
+            self.lic.invalidate();
+            self.nulls.clear();
+            self.forces = None;
+            self.particles.clear();
+        }
+        if reset_view {
+            self.reset_view();
+        }
+        if frame_charges {
+            self.frame_charges();
+        }
+        if toggle_projection {
+            self.toggle_projection();
+        }
+        if let Some(axis_view) = axis_view {
+            self.snap_view(axis_view);
+        }
     }
 }
 
@@ -555,32 +1762,160 @@ widget_ids! {
     INSTRUCTIONS_1,
     INSTRUCTIONS_2,
     CHOOSE_TEXT,
-    FIELDBTN_ONE,
-    FIELDBTN_TWO,
-    FIELDBTN_THREE,
-    FIELDBTN_CAP,
-    SLIDER_NP_INTRO,
-    SLIDER_NP_0,
-    SLIDER_NP_0_LC,
-    SLIDER_NP_0_SC,
-    SLIDER_NP_0_L,
-    SLIDER_NP_0_S,
-    SLIDER_NP_1,
-    SLIDER_NP_1_LC,
-    SLIDER_NP_1_SC,
-    SLIDER_NP_1_L,
-    SLIDER_NP_1_S,
-    SLIDER_SAME_INTRO,
-    SLIDER_SAME_0,
-    SLIDER_SAME_0_LC,
-    SLIDER_SAME_0_SC,
-    SLIDER_SAME_0_L,
-    SLIDER_SAME_0_S,
-    SLIDER_SAME_1,
-    SLIDER_SAME_1_LC,
-    SLIDER_SAME_1_SC,
-    SLIDER_SAME_1_L,
-    SLIDER_SAME_1_S,
-    INVERT_CHARGES_BTN,
-    TFGHRY_DESCRIPTION,
+    SCENE_BTN with 16, // MAX_SCENES
+    SCENE_LOAD_TEXT,
+    SCENE_CONTROL with 8, // MAX_SCENE_CONTROLS
+    SCENE_CONTROL_LC with 8,
+    SCENE_CONTROL_SC with 8,
+    SCENE_CONTROL_L with 8,
+    SCENE_CONTROL_S with 8,
+    DYNAMICS_BTN,
+    DYNAMICS_RESET_BTN,
+    DYNAMICS_TEXT,
+    GRID_BTN,
+    GRID_X,
+    GRID_X_LC,
+    GRID_X_SC,
+    GRID_X_L,
+    GRID_X_S,
+    GRID_Y,
+    GRID_Y_LC,
+    GRID_Y_SC,
+    GRID_Y_L,
+    GRID_Y_S,
+    GRID_Z,
+    GRID_Z_LC,
+    GRID_Z_SC,
+    GRID_Z_L,
+    GRID_Z_S,
+    GRID_SPACING,
+    GRID_SPACING_LC,
+    GRID_SPACING_SC,
+    GRID_SPACING_L,
+    GRID_SPACING_S,
+    GRID_ADAPTIVE_BTN,
+    GRID_BUDGET,
+    GRID_BUDGET_LC,
+    GRID_BUDGET_SC,
+    GRID_BUDGET_L,
+    GRID_BUDGET_S,
+    RESET_VIEW_BTN,
+    FRAME_CHARGES_BTN,
+    VIEW_FRONT_BTN,
+    VIEW_TOP_BTN,
+    VIEW_SIDE_BTN,
+    ORIENTATION_TEXT,
+    PROJECTION_BTN,
+    SNAP_BTN,
+    EDITOR_BTN,
+    EDITOR_PREV_BTN,
+    EDITOR_NEXT_BTN,
+    EDITOR_PAGE_TEXT,
+    EDITOR_ROW with 6, // EDITOR_ROWS
+    EDITOR_VISIBLE_BTN with 6, // EDITOR_ROWS
+    EDITOR_VALUES_TEXT,
+    EDITOR_VALUE_BOX with 4,
+    EDITOR_ADD_BTN,
+    EDITOR_REMOVE_BTN,
+    EDITOR_DUPLICATE_BTN,
+    EDITOR_MIRROR_BTN,
+    FOV,
+    FOV_LC,
+    FOV_SC,
+    FOV_L,
+    FOV_S,
+    DEPTH_BTN,
+    ARROW_STYLE_BTN,
+    DEPTH_SORT_BTN,
+    DEPTH_WIDTH_BTN,
+    DEPTH_FADE_BTN,
+    DEPTH_FOG_BTN,
+    FOG_START,
+    FOG_START_LC,
+    FOG_START_SC,
+    FOG_START_L,
+    FOG_START_S,
+    FOG_END,
+    FOG_END_LC,
+    FOG_END_SC,
+    FOG_END_L,
+    FOG_END_S,
+    LIC_BTN,
+    LIC_AXES_BTN,
+    LIC_EXPORT_BTN,
+    LIC_OFFSET,
+    LIC_OFFSET_LC,
+    LIC_OFFSET_SC,
+    LIC_OFFSET_L,
+    LIC_OFFSET_S,
+    LIC_EXPORT_TEXT,
+    SWEEP_BTN,
+    SWEEP_PLAY_BTN,
+    SWEEP_PARAM_BTN,
+    SWEEP_CURVE_BTN,
+    SWEEP_REPEAT_BTN,
+    SWEEP_FROM,
+    SWEEP_FROM_LC,
+    SWEEP_FROM_SC,
+    SWEEP_FROM_L,
+    SWEEP_FROM_S,
+    SWEEP_TO,
+    SWEEP_TO_LC,
+    SWEEP_TO_SC,
+    SWEEP_TO_L,
+    SWEEP_TO_S,
+    SWEEP_DURATION_SLIDER,
+    SWEEP_DURATION_LC,
+    SWEEP_DURATION_SC,
+    SWEEP_DURATION_L,
+    SWEEP_DURATION_S,
+    PROBE_BTN,
+    PROBE_CLEAR_BTN,
+    PROBE_COORDS_TEXT,
+    PROBE_COORD_BOX with 3,
+    PROBE_ERROR_TEXT,
+    PROBE_UNITS_TEXT,
+    PROBE_TEXT with 8, // MAX_PROBES
+    PROBE_REMOVE_BTN with 8, // MAX_PROBES
+    FORCES_BTN,
+    FORCES_SUMMARY_TEXT,
+    FORCES_TEXT with 10, // MAX_FORCE_READOUTS
+    FORCES_MORE_TEXT,
+    NULL_BTN,
+    NULL_SUMMARY_TEXT,
+    NULL_TEXT with 8, // MAX_NULL_READOUTS
+    KEYFRAME_TEXT,
+    KEYFRAME_ADD_BTN,
+    KEYFRAME_REMOVE_BTN,
+    KEYFRAME_CLEAR_BTN,
+    KEYFRAME_PLAY_BTN,
+    KEYFRAME_LOOP_BTN,
+    KEYFRAME_SAVE_BTN,
+    KEYFRAME_SAVE_TEXT,
+    PARTICLES_BTN,
+    PARTICLE_COUNT_SLIDER,
+    PARTICLE_COUNT_LC,
+    PARTICLE_COUNT_SC,
+    PARTICLE_COUNT_L,
+    PARTICLE_COUNT_S,
+    PARTICLE_SPEED_SLIDER,
+    PARTICLE_SPEED_LC,
+    PARTICLE_SPEED_SC,
+    PARTICLE_SPEED_L,
+    PARTICLE_SPEED_S,
 }
+
+// The counts of the widget ids reserved above, which fail to compile unless
+// they match the limits in consts.rs
+#[allow(dead_code)]
+const SCENE_BTN_COUNT: [(); MAX_SCENES] = [(); 16];
+#[allow(dead_code)]
+const SCENE_CONTROL_COUNT: [(); MAX_SCENE_CONTROLS] = [(); 8];
+#[allow(dead_code)]
+const EDITOR_ROW_COUNT: [(); EDITOR_ROWS] = [(); 6];
+#[allow(dead_code)]
+const PROBE_TEXT_COUNT: [(); MAX_PROBES] = [(); 8];
+#[allow(dead_code)]
+const FORCES_TEXT_COUNT: [(); MAX_FORCE_READOUTS] = [(); 10];
+#[allow(dead_code)]
+const NULL_TEXT_COUNT: [(); MAX_NULL_READOUTS] = [(); 8];
@@ -0,0 +1,366 @@
+use std::f64::consts::PI;
+
+use pw;
+
+use rayon::prelude::*;
+
+use na::{self, Point3, Vector3, Norm};
+
+use field::{FieldView, VectorField};
+
+use util;
+use consts::*;
+
+// The structure of the field about a null point, from the eigenvalues of
+// the Jacobian of the field there. Away from the charges the field is curl
+// and divergence free, so the Jacobian is symmetric with real eigenvalues
+// summing to zero: every non-degenerate null point is a saddle.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NullKind {
+    // Two positive eigenvalues: field lines come in along a line and leave
+    // across a plane (eg. midway between two identical positive charges)
+    LineInPlaneOut,
+    // Two negative eigenvalues: field lines come in across a plane and
+    // leave along a line
+    PlaneInLineOut,
+    // An eigenvalue (nearly) vanishes, as where null points merge
+    Degenerate,
+}
+
+impl NullKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            NullKind::LineInPlaneOut => "saddle, in along a line, out across a plane",
+            NullKind::PlaneInLineOut => "saddle, in across a plane, out along a line",
+            NullKind::Degenerate => "degenerate",
+        }
+    }
+
+    fn color(&self) -> [f32; 4] {
+        match *self {
+            NullKind::LineInPlaneOut => NULL_POINT_COLORS[0],
+            NullKind::PlaneInLineOut => NULL_POINT_COLORS[1],
+            NullKind::Degenerate => NULL_POINT_COLORS[2],
+        }
+    }
+}
+
+// A point at which the field vanishes
+#[derive(Clone, Copy)]
+pub struct NullPoint {
+    // In the coordinates of the charges
+    pub loc: Point3<f64>,
+    // Of the Jacobian of the field, greatest first
+    pub eigenvalues: [f64; 3],
+    pub kind: NullKind,
+}
+
+// The null points of the field of the selected scene, found again after
+// the field changes. Out of date points are kept until then.
+pub struct NullPoints {
+    points: Vec<NullPoint>,
+    stale: bool,
+}
+
+impl NullPoints {
+    pub fn new() -> NullPoints {
+        NullPoints {
+            points: vec![],
+            stale: true,
+        }
+    }
+
+    // Mark the null points out of date, eg. after the charges changed
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    // Forget the null points, eg. on switching to another scene
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.stale = true;
+    }
+
+    // Find the null points of `field` if they are out of date. Returns
+    // whether they were found again.
+    pub fn update(&mut self, field: &FieldView) -> bool {
+        if !self.stale {
+            return false;
+        }
+        self.points = find(field);
+        self.stale = false;
+        true
+    }
+
+    pub fn points(&self) -> &[NullPoint] {
+        &self.points
+    }
+
+    // The null point `i`, for display
+    pub fn readout(&self, i: usize) -> String {
+        let null = &self.points()[i];
+        let (p, e) = (null.loc, null.eigenvalues);
+        format!("#{} at ({:.1}, {:.1}, {:.1}): {}\neigenvalues {:.3e}, {:.3e}, {:.3e}",
+                i + 1, p.x, p.y, p.z, null.kind.name(), e[0], e[1], e[2])
+    }
+
+    // Mark each null point with a ring, colored by its kind
+    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d, field: &FieldView, view: [f64; 4]) {
+        let to_camera = field.arrow_transforms().then(&field.camera());
+        let projection = field.projection();
+        let r = NULL_POINT_MARKER_SIZE;
+        for null in self.points() {
+            let p = to_camera.transform_point(&null.loc);
+            if !projection.in_front(&p) {
+                continue;
+            }
+            let s = projection.to_viewport(&p, view);
+            pw::Ellipse::new_border(null.kind.color(), 1.5)
+                .draw([s[0] - r, s[1] - r, r * 2.0, r * 2.0], &c.draw_state, c.transform, gl);
+        }
+    }
+}
+
+// Find the null points of `field` within its grid. The grid is divided into
+// cubic cells, NULL_POINT_CELLS_PER_SPACING of them along each spacing of
+// the arrows, and Newton iteration is started from the center of each cell
+// across whose corners every component of the field changes sign.
+pub fn find(field: &FieldView) -> Vec<NullPoint> {
+    let bounds = field.bounds();
+    let h = field.grid_spacing() / NULL_POINT_CELLS_PER_SPACING;
+    let count = |(lo, hi): (f64, f64)| util::f64_max(((hi - lo) / h).ceil(), 1.0) as usize;
+    let (nx, ny, nz) = (count(bounds[0]), count(bounds[1]), count(bounds[2]));
+    let corner = |i: usize, j: usize, k: usize| Point3::new(
+        bounds[0].0 + i as f64 * h,
+        bounds[1].0 + j as f64 * h,
+        bounds[2].0 + k as f64 * h);
+
+    // The field at the corners of the cells, indexed [i][j][k] in one Vec
+    let index = |i: usize, j: usize, k: usize| (i * (ny + 1) + j) * (nz + 1) + k;
+    let mut corners = vec![];
+    for i in 0..nx + 1 {
+        for j in 0..ny + 1 {
+            for k in 0..nz + 1 {
+                corners.push(corner(i, j, k));
+            }
+        }
+    }
+    let samples: Vec<Vector3<f64>> = corners.par_iter()
+        .map(|p| field.field_data_at(p).force_vec)
+        .collect();
+
+    let mut seeds = vec![];
+    for i in 0..nx {
+        for j in 0..ny {
+            for k in 0..nz {
+                // Signs seen of each component across the cell's corners
+                let (mut pos, mut neg) = ([false; 3], [false; 3]);
+                for c in 0..8 {
+                    let e = samples[index(i + (c & 1), j + ((c >> 1) & 1), k + ((c >> 2) & 1))];
+                    for (axis, &v) in [e.x, e.y, e.z].iter().enumerate() {
+                        pos[axis] |= v >= 0.0;
+                        neg[axis] |= v <= 0.0;
+                    }
+                }
+                if (0..3).all(|axis| pos[axis] && neg[axis]) {
+                    seeds.push(corner(i, j, k) + Vector3::new(h, h, h) * 0.5);
+                }
+            }
+        }
+    }
+
+    let found: Vec<Option<NullPoint>> = seeds.par_iter()
+        .map(|seed| newton(field, *seed, h))
+        .collect();
+    // Neighboring cells often converge to the same point
+    let mut points: Vec<NullPoint> = vec![];
+    for null in found.into_iter().filter_map(|null| null) {
+        let inside = (0..3).all(|axis| {
+            let v = [null.loc.x, null.loc.y, null.loc.z][axis];
+            v >= bounds[axis].0 && v <= bounds[axis].1
+        });
+        let duplicate = points.iter().any(|other| na::distance(&other.loc, &null.loc) < h * 0.1);
+        if inside && !duplicate {
+            points.push(null);
+        }
+    }
+    points
+}
+
+// Refine `seed` to a null point of `field` by Newton iteration on the field,
+// with the Jacobian estimated by central differences of step `h` *
+// NULL_POINT_DIFF_STEP. None unless the iteration converges.
+fn newton(field: &FieldView, seed: Point3<f64>, h: f64) -> Option<NullPoint> {
+    let step = h * NULL_POINT_DIFF_STEP;
+    let mut p = seed;
+    for _ in 0..NULL_POINT_MAX_ITERATIONS {
+        let e = field.field_data_at(&p).force_vec;
+        let dp = match solve(&jacobian(field, &p, step), &e) {
+            Some(dp) => dp,
+            None => return None,
+        };
+        p = p - dp;
+        // Too far from the seed's cell to be its null point
+        if na::distance(&p, &seed) > h * 2.0 {
+            return None;
+        }
+        if dp.norm() < h * NULL_POINT_TOLERANCE {
+            let eigenvalues = symmetric_eigenvalues(&jacobian(field, &p, step));
+            return Some(NullPoint {
+                loc: p,
+                eigenvalues: eigenvalues,
+                kind: classify(&eigenvalues),
+            });
+        }
+    }
+    None
+}
+
+// The Jacobian of the field at `p`, as rows: j[r][c] is the derivative of
+// component r of the field along axis c
+fn jacobian(field: &FieldView, p: &Point3<f64>, step: f64) -> [[f64; 3]; 3] {
+    let mut j = [[0.0; 3]; 3];
+    for c in 0..3 {
+        let mut d = Vector3::new(0.0, 0.0, 0.0);
+        match c {
+            0 => d.x = step,
+            1 => d.y = step,
+            _ => d.z = step,
+        }
+        let diff = (field.field_data_at(&(*p + d)).force_vec - field.field_data_at(&(*p - d)).force_vec) / (2.0 * step);
+        j[0][c] = diff.x;
+        j[1][c] = diff.y;
+        j[2][c] = diff.z;
+    }
+    j
+}
+
+fn det(m: &[[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+// x such that m x = b, by Cramer's rule. None if m is (nearly) singular.
+fn solve(m: &[[f64; 3]; 3], b: &Vector3<f64>) -> Option<Vector3<f64>> {
+    let d = det(m);
+    let scale = m.iter().flat_map(|row| row.iter()).fold(0.0, |a: f64, v| a.max(v.abs()));
+    if d.abs() <= 1e-12 * scale * scale * scale || !d.is_finite() {
+        return None;
+    }
+    let b = [b.x, b.y, b.z];
+    let mut x = [0.0; 3];
+    for c in 0..3 {
+        let mut mc = *m;
+        for r in 0..3 {
+            mc[r][c] = b[r];
+        }
+        x[c] = det(&mc) / d;
+    }
+    Some(Vector3::new(x[0], x[1], x[2]))
+}
+
+// Eigenvalues, greatest first, of the symmetric part of `m` (the Jacobian
+// of a curl free field is symmetric, up to the error of differencing)
+fn symmetric_eigenvalues(m: &[[f64; 3]; 3]) -> [f64; 3] {
+    let mut a = [[0.0; 3]; 3];
+    for r in 0..3 {
+        for c in 0..3 {
+            a[r][c] = 0.5 * (m[r][c] + m[c][r]);
+        }
+    }
+    let off = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+    let q = (a[0][0] + a[1][1] + a[2][2]) / 3.0;
+    let diag = [(a[0][0] - q), (a[1][1] - q), (a[2][2] - q)];
+    let p = ((diag[0] * diag[0] + diag[1] * diag[1] + diag[2] * diag[2] + 2.0 * off) / 6.0).sqrt();
+    if p == 0.0 {
+        return [q, q, q];
+    }
+    // The eigenvalues of (a - q I) / p are 2 cos of these angles
+    let mut b = a;
+    for i in 0..3 {
+        b[i][i] -= q;
+    }
+    for row in b.iter_mut() {
+        for v in row.iter_mut() {
+            *v /= p;
+        }
+    }
+    let r = util::f64_min(util::f64_max(det(&b) * 0.5, -1.0), 1.0);
+    let phi = r.acos() / 3.0;
+    let greatest = q + 2.0 * p * phi.cos();
+    let least = q + 2.0 * p * (phi + 2.0 * PI / 3.0).cos();
+    [greatest, 3.0 * q - greatest - least, least]
+}
+
+fn classify(eigenvalues: &[f64; 3]) -> NullKind {
+    let largest = eigenvalues.iter().fold(0.0, |a: f64, e| a.max(e.abs()));
+    if eigenvalues.iter().any(|e| e.abs() < largest * NULL_POINT_DEGENERACY) {
+        return NullKind::Degenerate;
+    }
+    if eigenvalues[1] > 0.0 { NullKind::LineInPlaneOut } else { NullKind::PlaneInLineOut }
+}
+
+#[cfg(test)]
+mod tests {
+    use na::{self, Point3, Vector3, Norm};
+
+    use field::VectorField;
+    use point_charge::{PointCharge, PointChargesFieldView};
+
+    use consts::*;
+
+    use super::*;
+
+    // Charges `q0` and `q1` placed as in the "Two opposite charges" scene
+    fn pair(q0: f64, q1: f64) -> PointChargesFieldView {
+        PointChargesFieldView::new(Vector3::new(0.0, -GRID_S_2, 75.0), 602.4, -6495.8, 6495.8, vec![
+            PointCharge::new(q0, Point3::new(5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+            PointCharge::new(q1, Point3::new(-5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+        ])
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * (1.0 + b.abs())
+    }
+
+    #[test]
+    fn equal_charges_have_one_saddle_midway() {
+        let view = pair(8.0, 8.0);
+        let points = find(&view);
+        assert_eq!(points.len(), 1);
+        let null = points[0];
+        assert!(na::distance(&null.loc, &Point3::new(0.0, GRID_S_2, GRID_S_2)) < 1e-6);
+        assert!(view.field_data_at(&null.loc).force_vec.norm() < 1e-6);
+        // The field spreads out across the plane between the charges and
+        // converges along the line joining them
+        let e = null.eigenvalues;
+        assert!(e[0] > 0.0 && e[1] > 0.0 && e[2] < 0.0);
+        assert!(close(e[0], e[1]));
+        // Divergence free, up to the error of differencing
+        assert!((e[0] + e[1] + e[2]).abs() < 1e-4 * e[0]);
+        assert!(null.kind == NullKind::LineInPlaneOut);
+    }
+
+    #[test]
+    fn opposite_charges_have_no_nulls() {
+        assert!(find(&pair(8.0, -8.0)).is_empty());
+    }
+
+    #[test]
+    fn eigenvalues_greatest_first() {
+        let e = symmetric_eigenvalues(&[[2.0, 1.0, 0.0], [1.0, 2.0, 0.0], [0.0, 0.0, -4.0]]);
+        assert!(close(e[0], 3.0) && close(e[1], 1.0) && close(e[2], -4.0));
+        assert!(classify(&e) == NullKind::LineInPlaneOut);
+        assert!(classify(&[4.0, -1.0, -3.0]) == NullKind::PlaneInLineOut);
+        assert!(classify(&[3.0, 1e-6, -3.0]) == NullKind::Degenerate);
+    }
+
+    #[test]
+    fn solve_inverts_matrix() {
+        let m = [[2.0, 1.0, 0.0], [1.0, 3.0, 1.0], [0.0, 1.0, 4.0]];
+        let x = solve(&m, &Vector3::new(3.0, 5.0, 5.0)).unwrap();
+        assert!(close(x.x, 1.0) && close(x.y, 1.0) && close(x.z, 1.0));
+        assert!(solve(&[[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 0.0, 1.0]], &Vector3::new(1.0, 1.0, 1.0)).is_none());
+    }
+}
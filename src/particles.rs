@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+use pw;
+
+use rayon::prelude::*;
+
+use na::{Point3, Norm};
+
+use field::{FieldView, VectorField};
+use draw_list::{DrawList, Primitive};
+use pose::Pose;
+
+use util::Rng;
+use consts::*;
+
+// A tracer carried along the field lines, with the points it recently
+// passed through
+struct Particle {
+    pos: Point3<f64>,
+    // Most recent first
+    trail: VecDeque<Point3<f64>>,
+    age: f64,
+    lifetime: f64,
+}
+
+// Tracers drifting along the direction of the field (at a constant speed,
+// whatever its strength), to show how it flows. This is not the motion of
+// charges in the field, which is simulated by `Dynamics`.
+pub struct Particles {
+    particles: Vec<Particle>,
+    rng: Rng,
+    // Number of particles kept alive
+    pub count: usize,
+    // Distance travelled per second, in grid spacings
+    pub speed: f64,
+}
+
+impl Particles {
+    pub fn new() -> Particles {
+        Particles {
+            particles: vec![],
+            rng: Rng::new(PARTICLE_SEED),
+            count: PARTICLE_COUNT,
+            speed: PARTICLE_SPEED,
+        }
+    }
+
+    // Discard every particle, eg. when another field is chosen
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    fn spawn(rng: &mut Rng, field: &FieldView) -> Particle {
+        let bounds = field.bounds();
+        let pos = Point3::new(
+            rng.range(bounds[0].0, bounds[0].1),
+            rng.range(bounds[1].0, bounds[1].1),
+            rng.range(bounds[2].0, bounds[2].1),
+        );
+        Particle {
+            pos: pos,
+            trail: VecDeque::with_capacity(PARTICLE_TRAIL_LEN),
+            age: 0.0,
+            // Staggered, so that particles do not all respawn at once
+            lifetime: rng.range(0.5, 1.5) * PARTICLE_LIFETIME,
+        }
+    }
+
+    // Move every particle `dt` seconds along the field, respawning those
+    // which left the grid, came to rest or outlived their lifetime
+    pub fn advance(&mut self, field: &FieldView, dt: f64) {
+        self.particles.truncate(self.count);
+        while self.particles.len() < self.count {
+            let p = Particles::spawn(&mut self.rng, field);
+            self.particles.push(p);
+        }
+
+        let step = self.speed * field.grid_spacing() * dt;
+        let bounds = field.bounds();
+        let direction = |p: &Point3<f64>| {
+            let v = field.field_data_at(p).force_vec;
+            let len = v.norm();
+            if len > 1e-12 { Some(v / len) } else { None }
+        };
+        let dead: Vec<bool> = self.particles.par_iter_mut()
+            .map(|particle| {
+                // Midpoint method, so that particles follow curved field
+                // lines rather than drifting off them
+                let next = direction(&particle.pos)
+                    .and_then(|d| direction(&(particle.pos + d * (step * 0.5))))
+                    .map(|d| particle.pos + d * step);
+                particle.age += dt;
+                match next {
+                    Some(next) => {
+                        particle.trail.push_front(particle.pos);
+                        particle.trail.truncate(PARTICLE_TRAIL_LEN);
+                        particle.pos = next;
+                        let coords = [next.x, next.y, next.z];
+                        let inside = (0..3).all(|i| coords[i] >= bounds[i].0 && coords[i] <= bounds[i].1);
+                        !inside || particle.age > particle.lifetime
+                    },
+                    None => true,
+                }
+            })
+            .collect();
+        for (particle, dead) in self.particles.iter_mut().zip(dead) {
+            if dead {
+                *particle = Particles::spawn(&mut self.rng, field);
+            }
+        }
+    }
+
+    // Draw each particle's trail, fading towards its tail, over the arrows
+    // of `field`
+    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d, field: &FieldView, view: [f64; 4]) {
+        self.draw_list(field, view).draw(c, gl);
+    }
+
+    pub fn draw_list(&self, field: &FieldView, view: [f64; 4]) -> DrawList {
+        let to_camera: Pose = field.arrow_transforms().then(&field.camera());
+        let projection = field.projection();
+        let cue = field.depth_cue();
+        let mut list = DrawList::new();
+        for particle in &self.particles {
+            let mut prev = to_camera.transform_point(&particle.pos);
+            for (i, p) in particle.trail.iter().enumerate() {
+                let p = to_camera.transform_point(p);
+                if let Some((t0, t1)) = projection.clip_segment(&prev, &p, view) {
+                    let d = p - prev;
+                    let (from, to) = (prev + d * t0, prev + d * t1);
+                    let (a, b) = (projection.to_viewport(&from, view), projection.to_viewport(&to, view));
+                    let depth = (from.z + to.z) * 0.5;
+                    let mut color = PARTICLE_COLOR;
+                    color[3] *= 1.0 - i as f32 / PARTICLE_TRAIL_LEN as f32;
+                    list.push(depth, Primitive::Arrow {
+                        line: [a[0], a[1], b[0], b[1]],
+                        head: 0.0,
+                        radius: cue.line_radius(PARTICLE_RADIUS, depth),
+                        color: cue.color(color, depth),
+                    });
+                }
+                prev = p;
+            }
+        }
+        if cue.sort {
+            list.sort_back_to_front();
+        }
+        list
+    }
+}
@@ -0,0 +1,69 @@
+use na::Point3;
+
+use field::FieldView;
+use point_charge::PointChargesFieldView;
+use pose::Pose;
+
+use util;
+use consts::*;
+
+// The charge drawn nearest to `pos` in the view, if `pos` is on its disc
+// or within PICK_RADIUS pixels of it. Nearer charges win ties.
+pub fn pick_charge(field: &PointChargesFieldView, pos: [f64; 2], view: [f64; 4]) -> Option<usize> {
+    let to_camera = field.arrow_transforms().then(&field.camera());
+    let projection = field.projection();
+    let mut best: Option<(usize, f64, f64)> = None; // (index, pixel distance, depth)
+    for (i, chg) in field.charges.iter().enumerate() {
+        let p = to_camera.transform_point(&chg.loc);
+        if !chg.visible || !projection.in_front(&p) {
+            continue;
+        }
+        let screen = projection.to_viewport(&p, view);
+        let dist = ((screen[0] - pos[0]).powi(2) + (screen[1] - pos[1]).powi(2)).sqrt();
+        if dist > util::f64_max(PICK_RADIUS, chg.disc_radius(projection, p.z, view)) {
+            continue;
+        }
+        let better = match best {
+            None => true,
+            Some((_, best_dist, best_depth)) =>
+                dist < best_dist - 1.0 || (dist < best_dist + 1.0 && p.z < best_depth),
+        };
+        if better {
+            best = Some((i, dist, p.z));
+        }
+    }
+    best.map(|(i, _, _)| i)
+}
+
+// A charge being dragged with the mouse. It moves within the plane through
+// its original location parallel to the screen.
+pub struct ChargeDrag {
+    pub index: usize,
+    // Depth in front of the camera of the plane of motion
+    depth: f64,
+}
+
+impl ChargeDrag {
+    pub fn new(field: &PointChargesFieldView, index: usize) -> ChargeDrag {
+        let to_camera = field.arrow_transforms().then(&field.camera());
+        ChargeDrag {
+            index: index,
+            depth: to_camera.transform_point(&field.charges[index].loc).z,
+        }
+    }
+
+    // Where the charge should be for the cursor to be at `pos`, rounded to
+    // multiples of CHARGE_SNAP_STEP if `snap`
+    pub fn location(&self, field: &PointChargesFieldView, pos: [f64; 2], view: [f64; 4], snap: bool) -> Point3<f64> {
+        let (origin, dir) = field.projection().ray(pos, view);
+        let on_plane = origin + dir * ((self.depth - origin.z) / dir.z);
+        let from_camera: Pose = field.arrow_transforms().then(&field.camera()).inverse();
+        let loc = from_camera.transform_point(&on_plane);
+        if snap {
+            let round = |x: f64| (x / CHARGE_SNAP_STEP).round() * CHARGE_SNAP_STEP;
+            Point3::new(round(loc.x), round(loc.y), round(loc.z))
+        } else {
+            loc
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use num::Zero;
+
+use na::{self, Point3};
+
+use field::{FieldData, VectorField};
+
+use super::PointCharge;
+use units::Units;
+
+use util::{self, Rng};
+use consts::*;
+
+// Maximum number of charges summed directly in a leaf of the octree
+const LEAF_CAPACITY: usize = 8;
+// Guards against endless subdivision when many charges coincide
+const MAX_DEPTH: u32 = 24;
+
+// Approximates the field of a large set of PointCharges using an octree
+// (Barnes-Hut). Far away groups of charges are replaced by a single charge
+// at their center of charge, making each evaluation O(log n) rather than
+// O(n) in the number of charges.
+pub struct BarnesHutField {
+    nodes: Vec<Node>,
+
+    // Opening criterion: a node of width `w` whose center is at distance
+    // `d` from the evaluated point is approximated when w / d < theta.
+    // Smaller values are more accurate; 0.0 gives direct summation.
+    pub theta: f64,
+
+    units: Units,
+}
+
+struct Node {
+    center: Point3<f64>,
+    width: f64,
+
+    // The charges of each sign are lumped separately, since a region whose
+    // net charge is (nearly) zero, like a patch of a capacitor with both
+    // plates in it, has no useful single center of charge
+    positive: PointCharge,
+    negative: PointCharge,
+
+    contents: Contents,
+}
+
+enum Contents {
+    Leaf(Vec<PointCharge>),
+    // Indices into `nodes` of the non-empty octants
+    Branch(Vec<usize>),
+}
+
+impl BarnesHutField {
+    pub fn new(charges: &[PointCharge], theta: f64, units: Units) -> BarnesHutField {
+        let mut tree = BarnesHutField {
+            nodes: vec![],
+            theta: theta,
+            units: units,
+        };
+        let charges: Vec<PointCharge> = charges.iter().filter(|chg| chg.visible).cloned().collect();
+        if charges.is_empty() {
+            return tree;
+        }
+
+        // Smallest cube containing every charge
+        let (mut lo, mut hi) = (charges[0].loc, charges[0].loc);
+        for chg in &charges {
+            lo.x = util::f64_min(lo.x, chg.loc.x);
+            lo.y = util::f64_min(lo.y, chg.loc.y);
+            lo.z = util::f64_min(lo.z, chg.loc.z);
+            hi.x = util::f64_max(hi.x, chg.loc.x);
+            hi.y = util::f64_max(hi.y, chg.loc.y);
+            hi.z = util::f64_max(hi.z, chg.loc.z);
+        }
+        let center = Point3::new((lo.x + hi.x) * 0.5, (lo.y + hi.y) * 0.5, (lo.z + hi.z) * 0.5);
+        let width = util::f64_max(util::f64_max(hi.x - lo.x, hi.y - lo.y), hi.z - lo.z);
+        // Pad slightly so that no charge lies exactly on the boundary
+        tree.build(charges, center, width * 1.001 + 1e-9, 0);
+        tree
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Add the node containing `charges` (and its descendants) to the tree,
+    // returning its index
+    fn build(&mut self, charges: Vec<PointCharge>, center: Point3<f64>, width: f64, depth: u32) -> usize {
+        let idx = self.nodes.len();
+        let (positive, negative) = monopoles(&charges);
+        self.nodes.push(Node {
+            center: center,
+            width: width,
+            positive: positive,
+            negative: negative,
+            contents: Contents::Leaf(vec![]),
+        });
+
+        let contents = if charges.len() <= LEAF_CAPACITY || depth >= MAX_DEPTH {
+            Contents::Leaf(charges)
+        } else {
+            let mut octants: Vec<Vec<PointCharge>> = (0..8).map(|_| vec![]).collect();
+            for chg in charges {
+                let i = octant(&center, &chg.loc);
+                octants[i].push(chg);
+            }
+            let q = width * 0.25;
+            let mut children = vec![];
+            for (i, octant_charges) in octants.into_iter().enumerate() {
+                if octant_charges.is_empty() {
+                    continue;
+                }
+                let child_center = Point3::new(
+                    center.x + if i & 1 != 0 { q } else { -q },
+                    center.y + if i & 2 != 0 { q } else { -q },
+                    center.z + if i & 4 != 0 { q } else { -q });
+                children.push(self.build(octant_charges, child_center, width * 0.5, depth + 1));
+            }
+            Contents::Branch(children)
+        };
+        self.nodes[idx].contents = contents;
+        idx
+    }
+}
+
+impl VectorField for BarnesHutField {
+    fn field_data_at(&self, p: &Point3<f64>) -> FieldData {
+        let mut field_data: FieldData = Zero::zero();
+        if self.nodes.is_empty() {
+            return field_data;
+        }
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            match node.contents {
+                Contents::Leaf(ref charges) => {
+                    for chg in charges {
+                        field_data = field_data + chg.field_data_at(p, &self.units);
+                    }
+                },
+                Contents::Branch(ref children) => {
+                    if node.width < self.theta * na::distance(&node.center, p) {
+                        for mono in &[&node.positive, &node.negative] {
+                            if mono.charge != 0.0 {
+                                field_data = field_data + mono.field_data_at(p, &self.units);
+                            }
+                        }
+                    } else {
+                        stack.extend(children.iter().cloned());
+                    }
+                },
+            }
+        }
+        field_data.update_norm();
+        field_data
+    }
+}
+
+// Index of the octant of the cube centered at `center` which contains `p`
+fn octant(center: &Point3<f64>, p: &Point3<f64>) -> usize {
+    (if p.x >= center.x { 1 } else { 0 })
+        | (if p.y >= center.y { 2 } else { 0 })
+        | (if p.z >= center.z { 4 } else { 0 })
+}
+
+// The total positive and total negative charge of `charges`, each located
+// at its own center of charge
+fn monopoles(charges: &[PointCharge]) -> (PointCharge, PointCharge) {
+    let mut pos = (0.0, 0.0, 0.0, 0.0);
+    let mut neg = (0.0, 0.0, 0.0, 0.0);
+    for chg in charges {
+        let acc = if chg.charge > 0.0 { &mut pos } else { &mut neg };
+        let w = chg.charge.abs();
+        acc.0 += chg.charge;
+        acc.1 += chg.loc.x * w;
+        acc.2 += chg.loc.y * w;
+        acc.3 += chg.loc.z * w;
+    }
+    let lump = |(q, x, y, z): (f64, f64, f64, f64)| {
+        if q == 0.0 {
+            PointCharge::new(0.0, na::origin())
+        } else {
+            let w = q.abs();
+            PointCharge::new(q, Point3::new(x / w, y / w, z / w))
+        }
+    };
+    (lump(pos), lump(neg))
+}
+
+// A pair of plates of opposite charges, each a `side` by `side` square of
+// unit charges, like a finely discretized capacitor. Used to test and
+// benchmark the approximation.
+pub fn plates(side: i64) -> Vec<PointCharge> {
+    let spacing = 12.0 * GRID_S / side as f64;
+    let mut charges = vec![];
+    for i in 0..side {
+        for j in 0..side {
+            let x = -6.0 * GRID_S + i as f64 * spacing;
+            let z = -6.0 * GRID_S + j as f64 * spacing;
+            charges.push(PointCharge::new(1.0, Point3::new(x, -4.0 * GRID_S, z)));
+            charges.push(PointCharge::new(-1.0, Point3::new(x, 4.0 * GRID_S, z)));
+        }
+    }
+    charges
+}
+
+// `count` random points between the `plates`, from a generator seeded with
+// `seed`
+pub fn samples_between_plates(count: usize, seed: u64) -> Vec<Point3<f64>> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| Point3::new(
+            rng.range(-4.0 * GRID_S, 4.0 * GRID_S),
+            rng.range(-3.5 * GRID_S, 3.5 * GRID_S),
+            rng.range(-4.0 * GRID_S, 4.0 * GRID_S)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Zero;
+
+    use na::{Point3, Norm};
+
+    use field::{FieldData, VectorField};
+    use point_charge::PointCharge;
+    use units::Units;
+
+    use super::*;
+
+    // A smaller version of the benchmark's plates
+    fn plates_and_samples() -> (Vec<PointCharge>, Vec<Point3<f64>>) {
+        (plates(16), samples_between_plates(100, 1))
+    }
+
+    // The greatest difference between the approximate and the direct field
+    // at any sample, relative to the RMS direct field strength
+    fn relative_error(charges: &[PointCharge], samples: &[Point3<f64>], theta: f64) -> f64 {
+        let units = Units::legacy();
+        let tree = BarnesHutField::new(charges, theta, units);
+        let direct: Vec<FieldData> = samples.iter()
+            .map(|p| charges.iter()
+                .map(|chg| chg.field_data_at(p, &units))
+                .fold(Zero::zero(), |f0: FieldData, f1| f0 + f1))
+            .collect();
+        let rms = (direct.iter()
+            .map(|f| f.force_vec.norm_squared())
+            .fold(0.0, |a, b| a + b) / direct.len() as f64).sqrt();
+        samples.iter().zip(direct.iter())
+            .map(|(p, d)| (tree.field_data_at(p).force_vec - d.force_vec).norm())
+            .fold(0.0, |a: f64, b| a.max(b)) / rms
+    }
+
+    #[test]
+    fn exact_when_theta_is_zero() {
+        let (charges, samples) = plates_and_samples();
+        assert!(relative_error(&charges, &samples, 0.0) < 1e-9);
+    }
+
+    // Monopole-only Barnes-Hut has error of order theta^2
+    #[test]
+    fn error_within_bound() {
+        let (charges, samples) = plates_and_samples();
+        for &theta in &[0.2, 0.5, 0.8] {
+            let error = relative_error(&charges, &samples, theta);
+            assert!(error <= theta * theta, "theta {}: relative error {} exceeds {}", theta, error, theta * theta);
+        }
+    }
+}
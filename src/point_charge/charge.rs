@@ -1,29 +1,70 @@
 use std::ops::Add;
 use std::ops::AddAssign;
 
+use num::Zero;
+
 use na::{self, Point3, Vector3, Translate};
 
-use field::{FieldData, VectorField};
+use field::FieldData;
+use units::Units;
+
+use consts::*;
 
+#[derive(Clone)]
 pub struct PointCharge {
     pub charge: f64,
     pub loc: Point3<f64>,
+
+    // Only used when the charges are simulated (see `dynamics`)
+    pub mass: f64,
+    pub velocity: Vector3<f64>,
+
+    // Hidden charges neither produce a field nor feel forces
+    pub visible: bool,
 }
 
 impl PointCharge {
     pub fn new(charge: f64, loc: Point3<f64>) -> PointCharge {
-        PointCharge { charge: charge, loc: loc }
+        PointCharge {
+            charge: charge,
+            loc: loc,
+            mass: DEFAULT_CHARGE_MASS,
+            velocity: Zero::zero(),
+            visible: true,
+        }
     }
-}
 
-const CHARGE_SCALE_FACTOR: f64 = 10000.0;
+    // Coulomb force exerted on `self` by `other`. `softening` is added
+    // to the distance in quadrature so that the force stays bounded as
+    // the two charges pass through each other.
+    pub fn force_from(&self, other: &PointCharge, softening: f64, units: &Units) -> Vector3<f64> {
+        if !self.visible || !other.visible {
+            return Zero::zero();
+        }
+        let r = self.loc - other.loc;
+        let dist_squared = na::distance_squared(&self.loc, &other.loc) + softening * softening;
+        let dist = dist_squared.sqrt();
+        r * (units.force_factor() * self.charge * other.charge / (dist_squared * dist))
+    }
+
+    // Potential energy of the pair (`self`, `other`), softened as in `force_from`
+    pub fn potential_energy_with(&self, other: &PointCharge, softening: f64, units: &Units) -> f64 {
+        if !self.visible || !other.visible {
+            return 0.0;
+        }
+        let dist_squared = na::distance_squared(&self.loc, &other.loc) + softening * softening;
+        units.energy_factor() * self.charge * other.charge / dist_squared.sqrt()
+    }
 
-impl VectorField for PointCharge {
-    fn field_data_at(&self, p: &Point3<f64>) -> FieldData {
+    // The field and potential of the charge at `p`, in `units`
+    pub fn field_data_at(&self, p: &Point3<f64>, units: &Units) -> FieldData {
+        if !self.visible {
+            return Zero::zero();
+        }
         let dist_squared = na::distance_squared(&self.loc, p);
         let dist = dist_squared.sqrt();
-        let force_mag = CHARGE_SCALE_FACTOR * self.charge / dist_squared;
-        let potential = CHARGE_SCALE_FACTOR * self.charge / dist;
+        let force_mag = units.field_factor() * self.charge / dist_squared;
+        let potential = units.potential_factor() * self.charge / dist;
         let unit_vec = (p.clone() - self.loc) / dist;
         FieldData {
             force_vec: unit_vec * force_mag,
@@ -35,10 +76,10 @@ impl VectorField for PointCharge {
 
 impl Translate<PointCharge> for Vector3<f64> {
     fn translate(&self, chg: &PointCharge) -> PointCharge {
-        PointCharge::new(chg.charge, self.translate(&chg.loc))
+        PointCharge { loc: self.translate(&chg.loc), ..chg.clone() }
     }
     fn inverse_translate(&self, chg: &PointCharge) -> PointCharge {
-        PointCharge::new(chg.charge, self.inverse_translate(&chg.loc))
+        PointCharge { loc: self.inverse_translate(&chg.loc), ..chg.clone() }
     }
 }
 
@@ -0,0 +1,96 @@
+use pw::{self, Transformed};
+
+use na::Point3;
+
+use projection::Projection;
+use pose::Pose;
+use draw_list::{DrawList, Primitive};
+
+use util;
+use consts::*;
+
+use super::PointCharge;
+
+impl PointCharge {
+    // Draw the charge as a disc, red if positive and blue if negative, at
+    // its location `p` relative to the camera. `highlighted` charges (the
+    // one controlled by TFGH,RY) are ringed, and `label`s are drawn beside
+    // the disc.
+    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d, glyphs: &mut pw::Glyphs,
+                projection: &Projection, p: &Point3<f64>, view: [f64; 4],
+                label: bool, highlighted: bool) {
+        if !self.visible || !projection.in_front(p) {
+            return;
+        }
+        let center = projection.to_viewport(p, view);
+        let r = self.disc_radius(projection, p.z, view);
+        let rect = [center[0] - r, center[1] - r, r * 2.0, r * 2.0];
+        pw::Ellipse::new(self.disc_color())
+            .border(pw::ellipse::Border { color: CHARGE_BORDER_COLOR, radius: 0.5 })
+            .draw(rect, &c.draw_state, c.transform, gl);
+        if highlighted {
+            let ring = r + 3.0;
+            pw::Ellipse::new_border(HIGHLIGHT_COLOR, 1.5)
+                .draw([center[0] - ring, center[1] - ring, ring * 2.0, ring * 2.0], &c.draw_state, c.transform, gl);
+        }
+        if label || highlighted {
+            pw::Text::new_color([0.0, 0.0, 0.0, 1.0], CHARGE_LABEL_FONT_SIZE)
+                .draw(&format!("{:+.1}", self.charge), glyphs, &c.draw_state,
+                      c.transform.trans(center[0] + r + 4.0, center[1] + r), gl);
+        }
+    }
+
+    pub fn disc_color(&self) -> [f32; 4] {
+        if self.charge >= 0.0 { POSITIVE_CHARGE_COLOR } else { NEGATIVE_CHARGE_COLOR }
+    }
+
+    // Radius in pixels of the disc drawn for the charge at `depth`. The
+    // disc spans CHARGE_RADIUS_PER_UNIT units per unit of charge, kept
+    // within limits so that tiny charges stay visible and large ones do
+    // not cover the view.
+    pub fn disc_radius(&self, projection: &Projection, depth: f64, view: [f64; 4]) -> f64 {
+        let r = self.charge.abs() * CHARGE_RADIUS_PER_UNIT * projection.pixels_per_unit(depth, view);
+        util::f64_min(util::f64_max(r, MIN_CHARGE_RADIUS_PX), MAX_CHARGE_RADIUS_PX)
+    }
+}
+
+// Draw the charges over the arrows, farthest first
+pub fn draw_charges(charges: &[PointCharge], c: pw::Context, gl: &mut pw::G2d, glyphs: &mut pw::Glyphs,
+                    projection: &Projection, to_camera: &Pose, view: [f64; 4], highlighted: Option<usize>) {
+    // Labelling hundreds of charges (as in the capacitor) would only
+    // obscure the view
+    let label = charges.len() <= MAX_LABELLED_CHARGES;
+    for (i, p) in far_first(charges, to_camera) {
+        charges[i].draw(c, gl, glyphs, projection, &p, view, label, highlighted == Some(i));
+    }
+}
+
+// The charges' discs, as drawn by `draw_charges` but without labels or
+// highlighting
+pub fn charge_discs(charges: &[PointCharge], projection: &Projection, to_camera: &Pose, view: [f64; 4]) -> DrawList {
+    let mut list = DrawList::new();
+    for (i, p) in far_first(charges, to_camera) {
+        let chg = &charges[i];
+        if !chg.visible || !projection.in_front(&p) {
+            continue;
+        }
+        list.push(p.z, Primitive::Disc {
+            center: projection.to_viewport(&p, view),
+            radius: chg.disc_radius(projection, p.z, view),
+            color: chg.disc_color(),
+            border: CHARGE_BORDER_COLOR,
+        });
+    }
+    list
+}
+
+// Indices of the charges and their locations relative to the camera,
+// farthest first
+fn far_first(charges: &[PointCharge], to_camera: &Pose) -> Vec<(usize, Point3<f64>)> {
+    let mut order: Vec<(usize, Point3<f64>)> = charges.iter()
+        .map(|chg| to_camera.transform_point(&chg.loc))
+        .enumerate()
+        .collect();
+    order.sort_by(|a, b| b.1.z.partial_cmp(&a.1.z).unwrap_or(::std::cmp::Ordering::Equal));
+    order
+}
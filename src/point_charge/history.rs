@@ -0,0 +1,137 @@
+use na::Point3;
+
+use super::PointCharge;
+
+use consts::*;
+
+// A reversible change to the charges of a PointChargesFieldView
+#[derive(Clone)]
+pub enum Edit {
+    SetCharge { index: usize, old: f64, new: f64 },
+    SetLoc { index: usize, old: Point3<f64>, new: Point3<f64> },
+    SetVisible { index: usize, old: bool, new: bool },
+    Add { index: usize, charge: PointCharge },
+    Remove { index: usize, charge: PointCharge },
+    // (least_pot, greatest_pot), which set the shading of the arrows
+    SetPotRange { old: (f64, f64), new: (f64, f64) },
+    // Edits made by a single action, undone together
+    Group(Vec<Edit>),
+}
+
+impl Edit {
+    // The edit which undoes this one
+    pub fn inverse(&self) -> Edit {
+        match *self {
+            Edit::SetCharge { index, old, new } => Edit::SetCharge { index: index, old: new, new: old },
+            Edit::SetLoc { index, old, new } => Edit::SetLoc { index: index, old: new, new: old },
+            Edit::SetVisible { index, old, new } => Edit::SetVisible { index: index, old: new, new: old },
+            Edit::Add { index, ref charge } => Edit::Remove { index: index, charge: charge.clone() },
+            Edit::Remove { index, ref charge } => Edit::Add { index: index, charge: charge.clone() },
+            Edit::SetPotRange { old, new } => Edit::SetPotRange { old: new, new: old },
+            Edit::Group(ref edits) => Edit::Group(edits.iter().rev().map(|e| e.inverse()).collect()),
+        }
+    }
+
+    // Absorb `next` if it continues this edit (eg. the next step of a
+    // slider drag), returning whether it did
+    fn coalesce(&mut self, next: &Edit) -> bool {
+        match (self, next) {
+            (&mut Edit::SetCharge { index, ref mut new, .. }, &Edit::SetCharge { index: i, new: next_new, .. })
+                if index == i => {
+                *new = next_new;
+                true
+            },
+            (&mut Edit::SetLoc { index, ref mut new, .. }, &Edit::SetLoc { index: i, new: next_new, .. })
+                if index == i => {
+                *new = next_new;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+// The edits which can be undone and redone. Consecutive edits of the same
+// property of the same charge are merged into one until the history is
+// sealed, so that a whole drag of a slider or a charge is undone at once.
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    // Edits of the group being recorded, if any
+    group: Option<Vec<Edit>>,
+    // Whether the next edit starts a new step rather than continuing the
+    // last one
+    sealed: bool,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo: vec![],
+            redo: vec![],
+            group: None,
+            sealed: true,
+        }
+    }
+
+    pub fn record(&mut self, edit: Edit) {
+        self.redo.clear();
+        if let Some(ref mut group) = self.group {
+            group.push(edit);
+            return;
+        }
+        let coalesced = !self.sealed && self.undo.last_mut().map_or(false, |last| last.coalesce(&edit));
+        if !coalesced {
+            self.undo.push(edit);
+            if self.undo.len() > HISTORY_LIMIT {
+                self.undo.remove(0);
+            }
+        }
+        self.sealed = false;
+    }
+
+    // End the current step: the next edit is never merged into it
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    // Record the following edits, until `end_group`, as a single step
+    pub fn begin_group(&mut self) {
+        self.group = Some(vec![]);
+    }
+
+    pub fn end_group(&mut self) {
+        if let Some(edits) = self.group.take() {
+            if !edits.is_empty() {
+                self.record(Edit::Group(edits));
+            }
+            self.sealed = true;
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // The edit to revert, which is then available to redo
+    pub fn take_undo(&mut self) -> Option<Edit> {
+        self.sealed = true;
+        self.undo.pop().map(|edit| {
+            self.redo.push(edit.clone());
+            edit
+        })
+    }
+
+    // The edit to reapply, which is then available to undo again
+    pub fn take_redo(&mut self) -> Option<Edit> {
+        self.sealed = true;
+        self.redo.pop().map(|edit| {
+            self.undo.push(edit.clone());
+            edit
+        })
+    }
+}
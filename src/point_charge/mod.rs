@@ -1,15 +1,33 @@
-use num::{One, Zero};
+use std::mem;
+
+use num::Zero;
 
 use pw;
 
-use na::{Point3, Vector3, Matrix4, PerspectiveMatrix3};
+use rayon::prelude::*;
+
+use na::{Point3, Vector3};
 
-use field::{FieldData, VectorField, FieldView};
+use field::{self, FieldData, VectorField, FieldView, GridPoint, Bounds};
 
 mod charge;
 pub use self::charge::*;
 
-use arrow::Arrow;
+mod barnes_hut;
+pub use self::barnes_hut::*;
+
+mod history;
+pub use self::history::*;
+
+mod draw;
+pub use self::draw::*;
+
+use arrow::{Arrow, ArrowStyle};
+use pose::Pose;
+use projection::Projection;
+use depth_cue::DepthCue;
+use draw_list::DrawList;
+use units::Units;
 
 use util;
 use consts::*;
@@ -22,6 +40,9 @@ pub struct PointChargesFieldView {
     pub greatest_pot: f64,
     pub least_pot: f64,
 
+    // What the locations and charges mean physically
+    pub units: Units,
+
     // The arrows describing the field strengths
     arrows: Vec<Arrow>,
 
@@ -29,25 +50,60 @@ pub struct PointChargesFieldView {
     // of the PointChargesFieldView (not to the camera). With this we can move
     // the location of a charge, rebuild the field, and then reapply
     // arrow_transforms to put the field where the user expects it
-    arrow_transforms: Matrix4<f64>,
+    arrow_transforms: Pose,
 
     // The transformation from absolute positions (as in `arrows`) to
     // positions relative to the camera's position and orientation
-    camera: Matrix4<f64>,
+    camera: Pose,
+    // The camera as it was created, for resetting the view
+    initial_camera: Pose,
 
     // The bounds of the grid in which we are viewing the field
-    x_range: (i64, i64),
-    y_range: (i64, i64),
-    z_range: (i64, i64),
+    bounds: Bounds,
+    // Distance between neighboring arrows (of the coarsest level, if
+    // the grid is adaptive)
+    grid_spacing: f64,
+    // Whether arrows are concentrated where the field varies rapidly,
+    // and the number of arrows to which the grid is then refined
+    pub adaptive_grid: bool,
+    pub arrow_budget: usize,
 
     // For getting to 2-space
-    persp: PerspectiveMatrix3<f64>,
+    projection: Projection,
+    depth_cue: DepthCue,
+    arrow_style: ArrowStyle,
+
+    // Accuracy of the Barnes-Hut approximation used when there are at
+    // least BARNES_HUT_MIN_CHARGES charges (see `BarnesHutField::theta`)
+    pub barnes_hut_theta: f64,
+    // Octree over `charges`, rebuilt before each population of the field
+    barnes_hut: Option<BarnesHutField>,
+
+    // Cache for incremental rebuilds: the contribution of each charge to the
+    // field at each grid point (indexed [charge][point]), and their sums.
+    // Moving or rescaling one charge then only requires recomputing its own
+    // contributions.
+    grid: Vec<GridPoint>,
+    contributions: Vec<Vec<FieldData>>,
+    totals: Vec<FieldData>,
+    // Indices of the charges changed since the arrows were last built
+    changed_charges: Vec<usize>,
+    all_charges_changed: bool,
+    // Incremental updates since `totals` were last summed from scratch
+    // (see INCREMENTAL_RESYNC_INTERVAL)
+    updates_since_resync: u32,
+
+    // Edits made through the methods below, for undo and redo
+    history: History,
 }
 
 impl VectorField for PointChargesFieldView {
     fn field_data_at(&self, p: &Point3<f64>) -> FieldData {
+        if let Some(ref tree) = self.barnes_hut {
+            return tree.field_data_at(p);
+        }
         let mut field_data: FieldData = self.charges.iter()
-            .map(|chg| chg.field_data_at(&p))
+            .map(|chg| chg.field_data_at(&p, &self.units))
             .fold(Zero::zero(), |f0, f1| f0 + f1);
         field_data.update_norm();
         field_data
@@ -59,21 +115,38 @@ impl PointChargesFieldView {
         -> PointChargesFieldView {
         PointChargesFieldView {
             arrows: vec![],
-            arrow_transforms: One::one(),
-            camera: util::translation_mat4(camera_offset),
-            persp: PerspectiveMatrix3::new(1.0, 200.0, NEAR_PLANE_Z, FAR_PLANE_Z),
+            arrow_transforms: Pose::identity(),
+            camera: Pose::translation(camera_offset),
+            initial_camera: Pose::translation(camera_offset),
+            projection: Projection::new(),
+            depth_cue: DepthCue::new(),
+            arrow_style: ArrowStyle::Lines,
             charges: charges,
 
             // Ranges in x,y,z in which we will draw the field vectors
             // These are expressed in terms on cubes in the grid, ie.,
             // in units of GRID_S voxels.
-            x_range: (-4, 6),
-            y_range: (-2, 4),
-            z_range: (-2, 4),
+            bounds: grid_bounds((-4, 6), (-2, 4), (-2, 4)),
+            grid_spacing: GRID_S,
+            adaptive_grid: false,
+            arrow_budget: DEFAULT_ARROW_BUDGET,
 
             greatest_field: greatest_field,
             greatest_pot: greatest_pot,
             least_pot: least_pot,
+            units: Units::legacy(),
+
+            barnes_hut_theta: BARNES_HUT_THETA,
+            barnes_hut: None,
+
+            grid: vec![],
+            contributions: vec![],
+            totals: vec![],
+            changed_charges: vec![],
+            all_charges_changed: true,
+            updates_since_resync: 0,
+
+            history: History::new(),
         }
     }
 
@@ -100,51 +173,402 @@ impl PointChargesFieldView {
         }
         PointChargesFieldView {
             arrows: vec![],
-            arrow_transforms: One::one(),
-            camera: util::translation_mat4(camera_trans),
-            persp: PerspectiveMatrix3::new(1.0, 200.0, NEAR_PLANE_Z, FAR_PLANE_Z),
+            arrow_transforms: Pose::identity(),
+            camera: Pose::translation(camera_trans),
+            initial_camera: Pose::translation(camera_trans),
+            projection: Projection::new(),
+            depth_cue: DepthCue::new(),
+            arrow_style: ArrowStyle::Lines,
             charges: charges,
 
             // Ranges in x,y,z in which we will draw the field vectors
             // These are expressed in terms on cubes in the grid, ie.,
             // in units of GRID_S voxels.
-            x_range: x_range,
-            y_range: y_range,
-            z_range: z_range,
+            bounds: grid_bounds(x_range, y_range, z_range),
+            grid_spacing: GRID_S,
+            adaptive_grid: false,
+            arrow_budget: DEFAULT_ARROW_BUDGET,
 
             greatest_field: greatest_field,
             greatest_pot: greatest_pot,
             least_pot: -greatest_pot,
+
+            barnes_hut_theta: BARNES_HUT_THETA,
+            barnes_hut: None,
+
+            grid: vec![],
+            contributions: vec![],
+            totals: vec![],
+            changed_charges: vec![],
+            all_charges_changed: true,
+            updates_since_resync: 0,
+
+            history: History::new(),
+        }
+    }
+
+    // Width of the grid along `axis` (0, 1, 2 for x, y, z)
+    pub fn extent(&self, axis: usize) -> f64 {
+        self.bounds[axis].1 - self.bounds[axis].0
+    }
+
+    pub fn set_bounds(&mut self, bounds: Bounds) {
+        self.bounds = bounds;
+        self.set_grid_spacing(self.grid_spacing);
+    }
+
+    // Resize the grid along `axis`, keeping it centered where it was
+    pub fn set_extent(&mut self, axis: usize, width: f64) {
+        let (lo, hi) = self.bounds[axis];
+        let mid = (lo + hi) * 0.5;
+        self.bounds[axis] = (mid - width * 0.5, mid + width * 0.5);
+        self.set_grid_spacing(self.grid_spacing);
+    }
+
+    // Set the distance between arrows, increasing it if needed to keep the
+    // regular grid below MAX_UNIFORM_ARROWS arrows
+    pub fn set_grid_spacing(&mut self, spacing: f64) {
+        let volume = self.extent(0) * self.extent(1) * self.extent(2);
+        let min_spacing = (volume / MAX_UNIFORM_ARROWS as f64).cbrt();
+        self.grid_spacing = util::f64_max(spacing, min_spacing);
+    }
+
+    // Locations of the charges after the transformations applied to the
+    // arrows, ie. where they appear among the arrows
+    pub fn transformed_charge_locs(&self) -> Vec<Point3<f64>> {
+        self.charges.iter()
+            .map(|chg| self.arrow_transforms.transform_point(&chg.loc))
+            .collect()
+    }
+
+    // Draw the charges over the arrows drawn by `render`, ringing the
+    // `highlighted` one
+    pub fn render_charges(&self, c: pw::Context, gl: &mut pw::G2d, glyphs: &mut pw::Glyphs,
+                          view: [f64; 4], highlighted: Option<usize>) {
+        let to_camera = self.arrow_transforms.then(&self.camera);
+        draw_charges(&self.charges, c, gl, glyphs, &self.projection, &to_camera, view, highlighted);
+    }
+
+    // The charges as discs, without labels, for drawing where there is no
+    // font (see `raster`)
+    pub fn charges_draw_list(&self, view: [f64; 4]) -> DrawList {
+        let to_camera = self.arrow_transforms.then(&self.camera);
+        charge_discs(&self.charges, &self.projection, &to_camera, view)
+    }
+
+    // The methods below change the charges and record the change in the
+    // history, so that it can be undone
+
+    pub fn set_charge(&mut self, i: usize, charge: f64) {
+        self.edit(Edit::SetCharge { index: i, old: self.charges[i].charge, new: charge });
+    }
+
+    // Add a charge, returning its index
+    pub fn add_charge(&mut self, chg: PointCharge) -> usize {
+        let i = self.charges.len();
+        self.edit(Edit::Add { index: i, charge: chg });
+        i
+    }
+
+    pub fn remove_charge(&mut self, i: usize) -> PointCharge {
+        let chg = self.charges[i].clone();
+        self.edit(Edit::Remove { index: i, charge: chg.clone() });
+        chg
+    }
+
+    pub fn set_charge_visible(&mut self, i: usize, visible: bool) {
+        self.edit(Edit::SetVisible { index: i, old: self.charges[i].visible, new: visible });
+    }
+
+    pub fn set_charge_loc(&mut self, i: usize, loc: Point3<f64>) {
+        self.edit(Edit::SetLoc { index: i, old: self.charges[i].loc, new: loc });
+    }
+
+    pub fn move_charge(&mut self, i: usize, delta: Vector3<f64>) {
+        let loc = self.charges[i].loc + delta;
+        self.set_charge_loc(i, loc);
+    }
+
+    pub fn set_pot_range(&mut self, least_pot: f64, greatest_pot: f64) {
+        self.edit(Edit::SetPotRange {
+            old: (self.least_pot, self.greatest_pot),
+            new: (least_pot, greatest_pot),
+        });
+    }
+
+    // Negate every charge, as a single edit
+    pub fn invert_charges(&mut self) {
+        self.history.begin_group();
+        for i in 0..self.charges.len() {
+            let c = self.charges[i].charge;
+            self.set_charge(i, -c);
+        }
+        // Now negate and swap least_pot and greatest_pot, because
+        // potential value at any given point is negated by negating
+        // the charges. We maximize contrast by being precise with this
+        // to make it easier to see potential gradients.
+        let (least_pot, greatest_pot) = (self.least_pot, self.greatest_pot);
+        self.set_pot_range(-greatest_pot, -least_pot);
+        self.history.end_group();
+    }
+
+    // Replace all of the charges, as a single edit
+    pub fn replace_charges(&mut self, charges: Vec<PointCharge>) {
+        self.history.begin_group();
+        for i in (0..self.charges.len()).rev() {
+            self.remove_charge(i);
+        }
+        for chg in charges {
+            self.add_charge(chg);
+        }
+        self.history.end_group();
+    }
+
+    // Revert the last edit, returning whether there was one
+    pub fn undo(&mut self) -> bool {
+        match self.history.take_undo() {
+            Some(edit) => {
+                self.apply(&edit.inverse());
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Reapply the last undone edit, returning whether there was one
+    pub fn redo(&mut self) -> bool {
+        match self.history.take_redo() {
+            Some(edit) => {
+                self.apply(&edit);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    // Finish the current step of the history, eg. at the end of a drag,
+    // so that further edits of the same charge are undone separately
+    pub fn seal_history(&mut self) {
+        self.history.seal();
+    }
+
+    fn edit(&mut self, edit: Edit) {
+        self.apply(&edit);
+        self.history.record(edit);
+    }
+
+    fn apply(&mut self, edit: &Edit) {
+        match *edit {
+            Edit::SetCharge { index, new, .. } => {
+                self.charges[index].charge = new;
+                self.charge_changed(index);
+            },
+            Edit::SetLoc { index, new, .. } => {
+                self.charges[index].loc = new;
+                self.charge_changed(index);
+            },
+            Edit::SetVisible { index, new, .. } => {
+                self.charges[index].visible = new;
+                self.charge_changed(index);
+            },
+            Edit::Add { index, ref charge } => {
+                self.charges.insert(index, charge.clone());
+                self.charges_changed();
+            },
+            Edit::Remove { index, .. } => {
+                self.charges.remove(index);
+                self.charges_changed();
+            },
+            Edit::SetPotRange { new, .. } => {
+                self.least_pot = new.0;
+                self.greatest_pot = new.1;
+            },
+            Edit::Group(ref edits) => {
+                for edit in edits {
+                    self.apply(edit);
+                }
+            },
+        }
+    }
+
+    // Record that `charges[i]` was changed, so that the next `rebuild`
+    // only has to update its contributions
+    pub fn charge_changed(&mut self, i: usize) {
+        if !self.changed_charges.contains(&i) {
+            self.changed_charges.push(i);
+        }
+    }
+
+    // Record that any of the charges may have changed (or that charges were
+    // added or removed), so that the next `rebuild` starts from scratch
+    pub fn charges_changed(&mut self) {
+        self.all_charges_changed = true;
+    }
+
+    // Whether the contributions of the charges at `points` grid points are
+    // few enough to cache (see CONTRIBUTION_CACHE_BYTES)
+    fn can_cache_contributions(&self, points: usize) -> bool {
+        self.charges.len() < BARNES_HUT_MIN_CHARGES
+            && self.charges.len() * points * mem::size_of::<FieldData>() <= CONTRIBUTION_CACHE_BYTES
+    }
+
+    // Whether the next `rebuild` can reuse the cached contributions at
+    // `grid`. When no charge has changed, eg. when only the camera moved,
+    // they are all reused and only the arrows are made again from the totals.
+    fn can_rebuild_incrementally(&self, grid: &[GridPoint]) -> bool {
+        !self.all_charges_changed
+            && !self.adaptive_grid
+            && self.contributions.len() == self.charges.len()
+            && self.grid[..] == *grid
+    }
+
+    // Recompute the contribution of every charge at every point of `grid`
+    fn rebuild_contributions(&mut self, grid: Vec<GridPoint>) {
+        self.grid = grid;
+        self.contributions = {
+            let (grid, units) = (&self.grid, self.units);
+            self.charges.par_iter()
+                .map(|chg| grid.iter().map(|pt| chg.field_data_at(&pt.loc, &units)).collect::<Vec<FieldData>>())
+                .collect()
+        };
+        self.resync_totals();
+    }
+
+    // Sum `totals` from the cached contributions, discarding rounding
+    // error accumulated by incremental updates
+    fn resync_totals(&mut self) {
+        let mut totals: Vec<FieldData> = vec![Zero::zero(); self.grid.len()];
+        for contribution in &self.contributions {
+            for (total, data) in totals.iter_mut().zip(contribution.iter()) {
+                *total = *total + *data;
+            }
+        }
+        self.totals = totals;
+        self.updates_since_resync = 0;
+    }
+
+    // Replace the cached contributions of each changed charge
+    fn update_contributions(&mut self) {
+        let changed = ::std::mem::replace(&mut self.changed_charges, vec![]);
+        for i in changed {
+            let new: Vec<FieldData> = {
+                let (chg, units) = (&self.charges[i], self.units);
+                self.grid.par_iter().map(|pt| chg.field_data_at(&pt.loc, &units)).collect()
+            };
+            for (total, (old, new)) in self.totals.iter_mut().zip(self.contributions[i].iter().zip(new.iter())) {
+                *total = *total - *old + *new;
+            }
+            self.contributions[i] = new;
+            self.updates_since_resync += 1;
+        }
+        if self.updates_since_resync >= INCREMENTAL_RESYNC_INTERVAL {
+            self.resync_totals();
         }
     }
 }
 
 impl FieldView for PointChargesFieldView {
-    fn ranges(&self) -> ((i64, i64), (i64, i64), (i64, i64)) {
-        (self.x_range, self.y_range, self.z_range)
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn grid_spacing(&self) -> f64 {
+        self.grid_spacing
+    }
+
+    fn grid_points(&self) -> Vec<GridPoint> {
+        if self.adaptive_grid {
+            field::adaptive_grid(self, &self.bounds, self.grid_spacing, self.arrow_budget)
+        } else {
+            field::uniform_grid(&self.bounds, self.grid_spacing)
+        }
+    }
+
+    fn point_charges(&self) -> Option<&PointChargesFieldView> {
+        Some(self)
+    }
+
+    fn point_charges_mut(&mut self) -> Option<&mut PointChargesFieldView> {
+        Some(self)
+    }
+
+    fn units(&self) -> Units {
+        self.units
+    }
+
+    fn prepare_field(&mut self) {
+        self.barnes_hut = if self.charges.len() >= BARNES_HUT_MIN_CHARGES {
+            Some(BarnesHutField::new(&self.charges, self.barnes_hut_theta, self.units))
+        } else {
+            None
+        };
     }
 
     fn set_arrows(&mut self, arrows: Vec<Arrow>) {
         self.arrows = arrows;
     }
 
-    fn render(&self, c: pw::Context, gl: &mut pw::G2d, view: [f64; 4]) {
-        // Clear the section on which we will draw
-        pw::Rectangle::new(pw::color::WHITE).draw(view, &c.draw_state, c.transform, gl);
+    fn arrows(&self) -> &[Arrow] {
+        &self.arrows
+    }
+
+    fn rebuild(&mut self) {
+        // Before the grid, which may be adapted to the field
+        self.prepare_field();
+        let grid = self.grid_points();
+        if !self.can_cache_contributions(grid.len()) {
+            // Too many charges or grid points to cache their contributions
+            self.grid = vec![];
+            self.contributions = vec![];
+            self.totals = vec![];
+            let arrows = grid.par_iter().map(|pt| self.arrow_at(pt)).collect();
+            self.arrows = arrows;
+        } else {
+            if self.can_rebuild_incrementally(&grid) {
+                self.update_contributions();
+            } else {
+                self.rebuild_contributions(grid);
+            }
+            let arrows = self.grid.iter().zip(self.totals.iter())
+                .map(|(pt, total)| {
+                    let mut field_data = *total;
+                    field_data.update_norm();
+                    self.arrow_for(pt, &field_data)
+                })
+                .collect();
+            self.arrows = arrows;
+        }
+        self.changed_charges.clear();
+        self.all_charges_changed = false;
+    }
 
-        let persp = &self.persp;
+    fn draw_list(&self, view: [f64; 4]) -> DrawList {
+        let projection = &self.projection;
         let cam = &self.camera;
+        let mut list = DrawList::new();
         for arrow in &self.arrows {
-            arrow.draw(c, gl, persp, cam, view);
+            arrow.draw(&mut list, self.arrow_style, projection, cam, &self.depth_cue, view);
+        }
+        if self.depth_cue.sort {
+            list.sort_back_to_front();
         }
+        list
     }
 
-    fn transform_arrows(&mut self, t: Matrix4<f64>) {
+    fn transform_arrows(&mut self, t: &Pose) {
         for arrow in self.arrows.iter_mut() {
-            arrow.map_transform(&t);
+            arrow.map_transform(t);
         }
         // Record t in arrow_transforms
-        self.arrow_transforms = t * self.arrow_transforms;
+        self.arrow_transforms = self.arrow_transforms.then(t);
     }
 
     fn reapply_arrow_transforms(&mut self) {
@@ -153,8 +577,48 @@ impl FieldView for PointChargesFieldView {
         }
     }
 
-    fn transform_camera(&mut self, t: Matrix4<f64>) {
-        self.camera = t * self.camera;
+    fn transform_camera(&mut self, t: &Pose) {
+        self.camera = self.camera.then(t);
+    }
+
+    fn reset_camera(&mut self) {
+        self.camera = self.initial_camera;
+    }
+
+    fn arrow_transforms(&self) -> Pose {
+        self.arrow_transforms
+    }
+
+    fn camera(&self) -> Pose {
+        self.camera
+    }
+
+    fn set_camera(&mut self, camera: Pose) {
+        self.camera = camera;
+    }
+
+    fn projection(&self) -> &Projection {
+        &self.projection
+    }
+
+    fn projection_mut(&mut self) -> &mut Projection {
+        &mut self.projection
+    }
+
+    fn depth_cue(&self) -> &DepthCue {
+        &self.depth_cue
+    }
+
+    fn depth_cue_mut(&mut self) -> &mut DepthCue {
+        &mut self.depth_cue
+    }
+
+    fn arrow_style(&self) -> ArrowStyle {
+        self.arrow_style
+    }
+
+    fn set_arrow_style(&mut self, style: ArrowStyle) {
+        self.arrow_style = style;
     }
 
     fn greatest_field(&self) -> f64 {
@@ -169,3 +633,176 @@ impl FieldView for PointChargesFieldView {
         self.least_pot
     }
 }
+
+// Bounds of a grid given in units of GRID_S voxels
+fn grid_bounds(x_range: (i64, i64), y_range: (i64, i64), z_range: (i64, i64)) -> Bounds {
+    let scale = |(lo, hi): (i64, i64)| (lo as f64 * GRID_S, hi as f64 * GRID_S);
+    [scale(x_range), scale(y_range), scale(z_range)]
+}
+
+#[cfg(test)]
+mod tests {
+    use na::{Point3, Vector3};
+
+    use field::{FieldView, VectorField};
+    use dynamics::Dynamics;
+
+    use consts::*;
+
+    use super::*;
+
+    fn two_charges() -> PointChargesFieldView {
+        PointChargesFieldView::new(Vector3::new(0.0, -GRID_S_2, 75.0), 602.4, -6495.8, 6495.8, vec![
+            PointCharge::new(8.0, Point3::new(5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+            PointCharge::new(-8.0, Point3::new(-5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+        ])
+    }
+
+    // The arrows of `view`, rebuilt from scratch so that the comparison is
+    // not affected by rounding in incremental rebuilds
+    fn arrows(view: &mut PointChargesFieldView) -> Vec<(Point3<f64>, Point3<f64>, f64, f64)> {
+        view.charges_changed();
+        view.rebuild();
+        view.arrows().iter().map(|a| (a.tail, a.head, a.field, a.potential)).collect()
+    }
+
+    // Make the edits of `edit` as one step, then check that undoing it
+    // restores the original arrows exactly and redoing it the edited ones
+    fn check_undo<F: FnOnce(&mut PointChargesFieldView)>(edit: F) {
+        let mut view = two_charges();
+        let original = arrows(&mut view);
+        edit(&mut view);
+        view.seal_history();
+        let edited = arrows(&mut view);
+        assert!(edited != original);
+
+        assert!(view.undo());
+        assert!(!view.can_undo());
+        // Exercise the incremental rebuild before rebuilding from scratch
+        view.rebuild();
+        assert_eq!(arrows(&mut view), original);
+        assert!(view.redo());
+        view.rebuild();
+        assert_eq!(arrows(&mut view), edited);
+    }
+
+    #[test]
+    fn undo_add() {
+        check_undo(|view| { view.add_charge(PointCharge::new(3.0, Point3::new(0.0, GRID_S, 0.0))); });
+    }
+
+    #[test]
+    fn undo_remove() {
+        check_undo(|view| { view.remove_charge(0); });
+    }
+
+    #[test]
+    fn undo_coalesced_set_charge() {
+        // A drag of a slider, which is undone as one step
+        check_undo(|view| for i in 0..10 {
+            view.set_charge(0, 8.0 - i as f64 * 1.5);
+        });
+    }
+
+    #[test]
+    fn undo_move_and_hide() {
+        check_undo(|view| {
+            view.history.begin_group();
+            view.move_charge(0, Vector3::new(CHARGE_MVMT_STEP, 0.0, -CHARGE_MVMT_STEP));
+            view.set_charge_visible(1, false);
+            view.history.end_group();
+        });
+    }
+
+    #[test]
+    fn undo_invert_charges() {
+        check_undo(|view| view.invert_charges());
+    }
+
+    #[test]
+    fn rebuild_without_changes_keeps_arrows() {
+        let mut view = two_charges();
+        let original = arrows(&mut view);
+        view.rebuild();
+        let arrows: Vec<_> = view.arrows().iter().map(|a| (a.tail, a.head, a.field, a.potential)).collect();
+        assert_eq!(arrows, original);
+    }
+
+    #[test]
+    fn fewer_charges_drop_octree() {
+        let mut view = two_charges();
+        let original = view.charges.clone();
+        let many = (0..BARNES_HUT_MIN_CHARGES)
+            .map(|i| PointCharge::new(1.0, Point3::new(i as f64, 200.0, 0.0)))
+            .collect();
+        view.replace_charges(many);
+        view.rebuild();
+        view.replace_charges(original);
+        view.rebuild();
+        let p = Point3::new(GRID_S, 0.0, GRID_S_2);
+        assert_eq!(view.field_data_at(&p).force_vec, two_charges().field_data_at(&p).force_vec);
+    }
+
+    #[test]
+    fn large_caches_not_kept() {
+        let mut view = two_charges();
+        assert!(view.can_cache_contributions(1000));
+        assert!(!view.can_cache_contributions(CONTRIBUTION_CACHE_BYTES));
+        view.rebuild();
+        assert!(!view.contributions.is_empty());
+    }
+
+    #[test]
+    fn undo_sequence() {
+        let mut view = two_charges();
+        let original = arrows(&mut view);
+        for i in 0..10 {
+            view.set_charge(0, 8.0 - i as f64 * 1.5);
+        }
+        view.seal_history();
+        view.move_charge(0, Vector3::new(CHARGE_MVMT_STEP, 0.0, -CHARGE_MVMT_STEP));
+        view.seal_history();
+        let i = view.add_charge(PointCharge::new(3.0, Point3::new(0.0, GRID_S, 0.0)));
+        view.set_charge_visible(i, false);
+        view.remove_charge(0);
+        view.invert_charges();
+        let edited = arrows(&mut view);
+
+        let mut undone = 0;
+        while view.undo() {
+            undone += 1;
+            view.rebuild();
+        }
+        assert_eq!(undone, 6);
+        assert_eq!(arrows(&mut view), original);
+        while view.redo() {
+            view.rebuild();
+        }
+        assert_eq!(arrows(&mut view), edited);
+    }
+
+    // Charges added during a simulation and then removed by resetting it
+    // must be in the history, or undoing the addition removes a charge
+    // which is no longer there
+    #[test]
+    fn undo_after_simulation_reset() {
+        let mut view = two_charges();
+        let original = arrows(&mut view);
+        let mut dynamics = Dynamics::new(&view.charges, view.units);
+        dynamics.advance(&mut view.charges, 0.1);
+        view.charges_changed();
+        view.add_charge(PointCharge::new(3.0, Point3::new(0.0, GRID_S, 0.0)));
+        dynamics.reset(&mut view);
+        assert_eq!(view.charges.len(), 2);
+        assert_eq!(arrows(&mut view), original);
+
+        // Undoing the reset brings back the simulated charges, then the
+        // addition is undone
+        assert!(view.undo());
+        assert_eq!(view.charges.len(), 3);
+        assert!(view.undo());
+        assert_eq!(view.charges.len(), 2);
+        assert!(!view.undo());
+        arrows(&mut view);
+    }
+}
@@ -0,0 +1,136 @@
+use std::f64::consts::PI;
+
+use na::{Point3, Vector3, Quaternion, UnitQuaternion};
+use num::Zero;
+
+// A rigid transformation: a rotation followed by a translation. The
+// rotation is kept as a unit quaternion which is renormalized after every
+// composition, so that accumulating many small rotations (as the keyboard
+// and mouse controls do) cannot introduce drift, shear or scaling.
+#[derive(Clone, Copy)]
+pub struct Pose {
+    pub rotation: UnitQuaternion<f64>,
+    pub translation: Vector3<f64>,
+}
+
+impl Pose {
+    pub fn identity() -> Pose {
+        Pose {
+            rotation: identity_quaternion(),
+            translation: Zero::zero(),
+        }
+    }
+
+    pub fn translation(v: Vector3<f64>) -> Pose {
+        Pose {
+            rotation: identity_quaternion(),
+            translation: v,
+        }
+    }
+
+    pub fn rotation(rotation: UnitQuaternion<f64>) -> Pose {
+        Pose {
+            rotation: rotation,
+            translation: Zero::zero(),
+        }
+    }
+
+    // Rotation by `x` about the x axis, then `y` about the y axis, then
+    // `z` about the z axis
+    pub fn euler(x: f64, y: f64, z: f64) -> Pose {
+        Pose::rotation(euler_quaternion(x, y, z))
+    }
+
+    // The transformation applying `self`, then `next`
+    pub fn then(&self, next: &Pose) -> Pose {
+        Pose {
+            rotation: normalized(next.rotation * self.rotation),
+            translation: next.rotate(&self.translation) + next.translation,
+        }
+    }
+
+    pub fn inverse(&self) -> Pose {
+        let q = *self.rotation.quaternion();
+        let inv = UnitQuaternion::new_with_quaternion(Quaternion::new(q.w, -q.i, -q.j, -q.k));
+        let inv_pose = Pose::rotation(inv);
+        Pose {
+            rotation: inv,
+            translation: -inv_pose.rotate(&self.translation),
+        }
+    }
+
+    pub fn rotate(&self, v: &Vector3<f64>) -> Vector3<f64> {
+        let m = rotation_columns(&self.rotation);
+        m[0] * v.x + m[1] * v.y + m[2] * v.z
+    }
+
+    pub fn transform_point(&self, p: &Point3<f64>) -> Point3<f64> {
+        Point3::new(0.0, 0.0, 0.0) + self.rotate(&p.to_vector()) + self.translation
+    }
+
+    // (yaw, pitch, roll) in degrees, ie. the rotations about the z, y and
+    // x axes which, applied in the reverse order, give the rotation
+    pub fn yaw_pitch_roll(&self) -> (f64, f64, f64) {
+        let m = rotation_columns(&self.rotation);
+        // Clamp: rounding can push the sine just past +-1
+        let pitch = (-m[0].z).max(-1.0).min(1.0).asin();
+        let yaw = m[0].y.atan2(m[0].x);
+        let roll = m[1].z.atan2(m[2].z);
+        (yaw * 180.0 / PI, pitch * 180.0 / PI, roll * 180.0 / PI)
+    }
+}
+
+pub fn identity_quaternion() -> UnitQuaternion<f64> {
+    UnitQuaternion::new_with_quaternion(Quaternion::new(1.0, 0.0, 0.0, 0.0))
+}
+
+pub fn euler_quaternion(x: f64, y: f64, z: f64) -> UnitQuaternion<f64> {
+    let about = |angle: f64, axis: usize| {
+        let (s, c) = ((angle * 0.5).sin(), (angle * 0.5).cos());
+        UnitQuaternion::new_with_quaternion(Quaternion::new(
+            c,
+            if axis == 0 { s } else { 0.0 },
+            if axis == 1 { s } else { 0.0 },
+            if axis == 2 { s } else { 0.0 }))
+    };
+    normalized(about(z, 2) * about(y, 1) * about(x, 0))
+}
+
+// Spherical linear interpolation from `a` (t = 0) to `b` (t = 1), the
+// short way around
+pub fn slerp(a: &UnitQuaternion<f64>, b: &UnitQuaternion<f64>, t: f64) -> UnitQuaternion<f64> {
+    let (qa, qb) = (a.quaternion(), b.quaternion());
+    let mut dot = qa.w * qb.w + qa.i * qb.i + qa.j * qb.j + qa.k * qb.k;
+    // q and -q are the same rotation; take the nearer of the two
+    let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+    dot *= sign;
+    let (wa, wb) = if dot > 0.9995 {
+        // Nearly the same rotation: interpolate linearly, avoiding the
+        // division by sin(angle) ~ 0 below
+        (1.0 - t, t)
+    } else {
+        let angle = dot.acos();
+        (((1.0 - t) * angle).sin() / angle.sin(), (t * angle).sin() / angle.sin())
+    };
+    let wb = wb * sign;
+    UnitQuaternion::new_with_quaternion(Quaternion::new(
+        wa * qa.w + wb * qb.w,
+        wa * qa.i + wb * qb.i,
+        wa * qa.j + wb * qb.j,
+        wa * qa.k + wb * qb.k))
+}
+
+fn normalized(q: UnitQuaternion<f64>) -> UnitQuaternion<f64> {
+    UnitQuaternion::new_with_quaternion(*q.quaternion())
+}
+
+// Columns of the rotation matrix of `q`
+fn rotation_columns(q: &UnitQuaternion<f64>) -> [Vector3<f64>; 3] {
+    let q = q.quaternion();
+    let (w, x, y, z) = (q.w, q.i, q.j, q.k);
+    [
+        Vector3::new(1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + w * z), 2.0 * (x * z - w * y)),
+        Vector3::new(2.0 * (x * y - w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + w * x)),
+        Vector3::new(2.0 * (x * z + w * y), 2.0 * (y * z - w * x), 1.0 - 2.0 * (x * x + y * y)),
+    ]
+}
@@ -0,0 +1,115 @@
+use pw::{self, Transformed};
+
+use na::Point3;
+
+use field::{FieldView, VectorField};
+use camera;
+use pose::Pose;
+use units::Quantity;
+
+use consts::*;
+
+// Points pinned in the field at which its value is read out, and the
+// state of the tools for placing them
+pub struct Probes {
+    // In the coordinates of the charges, so that probes stay put among
+    // them when the field is rotated
+    pub points: Vec<Point3<f64>>,
+    // Whether the next click in the view places a probe
+    pub placing: bool,
+    // Text of the x, y and z boxes for placing a probe by its coordinates
+    pub text: [String; 3],
+    // Why the probe last typed in could not be pinned, for display
+    pub error: Option<String>,
+}
+
+impl Probes {
+    pub fn new() -> Probes {
+        Probes {
+            points: vec![],
+            placing: false,
+            text: ["0.0".to_string(), "0.0".to_string(), "0.0".to_string()],
+            error: None,
+        }
+    }
+
+    // Pin a probe at `p`, unless MAX_PROBES are already pinned
+    pub fn add(&mut self, p: Point3<f64>) -> bool {
+        if self.points.len() >= MAX_PROBES {
+            return false;
+        }
+        self.points.push(p);
+        true
+    }
+
+    pub fn remove(&mut self, i: usize) {
+        if i < self.points.len() {
+            self.points.remove(i);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.placing = false;
+        self.error = None;
+    }
+
+    // Pin a probe at the coordinates typed in `text`
+    pub fn add_typed(&mut self) -> Result<(), String> {
+        let mut v = [0.0; 3];
+        for i in 0..3 {
+            v[i] = try!(self.text[i].trim().parse::<f64>()
+                .map_err(|_| format!("`{}` is not a number", self.text[i].trim())));
+        }
+        if self.add(Point3::new(v[0], v[1], v[2])) {
+            Ok(())
+        } else {
+            Err(format!("at most {} probes can be pinned", MAX_PROBES))
+        }
+    }
+
+    // Pin a probe under the cursor at `pos` in `view`, in the plane through
+    // the center of the grid parallel to the screen
+    pub fn place(&mut self, field: &FieldView, pos: [f64; 2], view: [f64; 4]) -> bool {
+        let depth = field.camera().transform_point(&camera::grid_center(field)).z;
+        let (origin, dir) = field.projection().ray(pos, view);
+        let on_plane = origin + dir * ((depth - origin.z) / dir.z);
+        let from_camera: Pose = field.arrow_transforms().then(&field.camera()).inverse();
+        self.placing = false;
+        self.add(from_camera.transform_point(&on_plane))
+    }
+
+    // The value of `field` at probe `i`, for display
+    pub fn readout(&self, field: &FieldView, i: usize) -> String {
+        let p = self.points[i];
+        let data = field.field_data_at(&p);
+        let e = data.force_vec;
+        let units = field.units();
+        let length = |x| units.format(x, Quantity::Length);
+        let strength = |x| units.format(x, Quantity::Field);
+        format!("#{} at ({}, {}, {})\nE = ({}, {}, {}), |E| = {}\nV = {}",
+                i + 1, length(p.x), length(p.y), length(p.z),
+                strength(e.x), strength(e.y), strength(e.z), strength(data.force_mag),
+                units.format(data.potential, Quantity::Potential))
+    }
+
+    // Mark each probe with a cross and its number
+    pub fn draw(&self, c: pw::Context, gl: &mut pw::G2d, glyphs: &mut pw::Glyphs, field: &FieldView, view: [f64; 4]) {
+        let to_camera = field.arrow_transforms().then(&field.camera());
+        let projection = field.projection();
+        let line = pw::Line::new(PROBE_COLOR, 1.0);
+        for (i, p) in self.points.iter().enumerate() {
+            let p = to_camera.transform_point(p);
+            if !projection.in_front(&p) {
+                continue;
+            }
+            let s = projection.to_viewport(&p, view);
+            let r = PROBE_MARKER_SIZE;
+            line.draw([s[0] - r, s[1] - r, s[0] + r, s[1] + r], &c.draw_state, c.transform, gl);
+            line.draw([s[0] - r, s[1] + r, s[0] + r, s[1] - r], &c.draw_state, c.transform, gl);
+            pw::Text::new_color(PROBE_COLOR, CHARGE_LABEL_FONT_SIZE)
+                .draw(&format!("#{}", i + 1), glyphs, &c.draw_state,
+                      c.transform.trans(s[0] + r + 2.0, s[1] - r), gl);
+        }
+    }
+}
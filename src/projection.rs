@@ -0,0 +1,247 @@
+use na::{self, Point3, Vector3};
+
+use util;
+use consts::*;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProjectionKind {
+    Perspective,
+    // Parallel projection, without perspective distortion, so that arrow
+    // lengths can be compared anywhere in the view
+    Orthographic,
+}
+
+// Maps points relative to the camera (which looks along +z, with +y down
+// the screen) onto the view
+pub struct Projection {
+    pub kind: ProjectionKind,
+    // Angle spanned by the smaller dimension of the view, in radians
+    pub fov: f64,
+    // Half of the width of camera space spanned by the smaller dimension
+    // of the view, when orthographic
+    pub ortho_half_width: f64,
+}
+
+impl Projection {
+    pub fn new() -> Projection {
+        Projection {
+            kind: ProjectionKind::Perspective,
+            fov: DEFAULT_FOV_DEGREES.to_radians(),
+            ortho_half_width: DEFAULT_ORTHO_HALF_WIDTH,
+        }
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        self.kind == ProjectionKind::Orthographic
+    }
+
+    // Tangent of half of `fov`
+    pub fn half_fov_tan(&self) -> f64 {
+        (self.fov * 0.5).tan()
+    }
+
+    // Half of the width of camera space visible, at `depth`, across the
+    // smaller dimension of the view
+    pub fn half_width_at(&self, depth: f64) -> f64 {
+        match self.kind {
+            ProjectionKind::Perspective => depth * self.half_fov_tan(),
+            ProjectionKind::Orthographic => self.ortho_half_width,
+        }
+    }
+
+    // Size in pixels of a unit length at `depth`
+    pub fn pixels_per_unit(&self, depth: f64, view: [f64; 4]) -> f64 {
+        0.5 * util::f64_min(view[2], view[3]) / self.half_width_at(depth)
+    }
+
+    // Whether `p` (relative to the camera) is in front of the near plane
+    pub fn in_front(&self, p: &Point3<f64>) -> bool {
+        p.z > NEAR_PLANE_Z
+    }
+
+    // The part of the segment from `a` to `b` (relative to the camera)
+    // within the near and far planes and the sides of `view`, if any, as
+    // the range [t0, t1] of t for which a + t(b - a) is visible
+    pub fn clip_segment(&self, a: &Point3<f64>, b: &Point3<f64>, view: [f64; 4]) -> Option<(f64, f64)> {
+        // Liang-Barsky: narrow the range of the parameter t of a + t(b - a)
+        // plane by plane
+        let (mut t0, mut t1) = (0.0, 1.0);
+        for &(ref normal, offset) in &self.clip_planes(view) {
+            let fa = na::dot(normal, &a.to_vector()) + offset;
+            let fb = na::dot(normal, &b.to_vector()) + offset;
+            if fa < 0.0 && fb < 0.0 {
+                return None;
+            }
+            if fa < 0.0 {
+                t0 = util::f64_max(t0, fa / (fa - fb));
+            } else if fb < 0.0 {
+                t1 = util::f64_min(t1, fa / (fa - fb));
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+
+    // The part of the convex polygon `pts` (relative to the camera) in
+    // front of the near plane, if any
+    pub fn clip_polygon(&self, pts: &[Point3<f64>]) -> Option<Vec<Point3<f64>>> {
+        // Sutherland-Hodgman, against the near plane only: the rest of the
+        // frustum is left to the scissor of the view
+        let mut out = vec![];
+        for i in 0..pts.len() {
+            let (p, q) = (pts[i], pts[(i + 1) % pts.len()]);
+            let (fp, fq) = (p.z - NEAR_PLANE_Z, q.z - NEAR_PLANE_Z);
+            if fp >= 0.0 {
+                out.push(p);
+            }
+            if (fp >= 0.0) != (fq >= 0.0) {
+                out.push(p + (q - p) * (fp / (fp - fq)));
+            }
+        }
+        if out.len() >= 3 { Some(out) } else { None }
+    }
+
+    // Planes bounding the visible region, as (normal, offset) pairs such
+    // that p is visible when normal . p + offset >= 0
+    fn clip_planes(&self, view: [f64; 4]) -> [(Vector3<f64>, f64); 6] {
+        let small = util::f64_min(view[2], view[3]);
+        // Ratios of the half-width and half-height of the view to the
+        // half-width at unit depth (perspective) or overall (orthographic)
+        let (kx, ky) = (view[2] / small, view[3] / small);
+        let near = (Vector3::new(0.0, 0.0, 1.0), -NEAR_PLANE_Z);
+        let far = (Vector3::new(0.0, 0.0, -1.0), FAR_PLANE_Z);
+        match self.kind {
+            ProjectionKind::Perspective => {
+                // |x| <= z * tan * kx, and likewise for y
+                let (sx, sy) = (self.half_fov_tan() * kx, self.half_fov_tan() * ky);
+                [
+                    near, far,
+                    (Vector3::new(-1.0, 0.0, sx), 0.0),
+                    (Vector3::new(1.0, 0.0, sx), 0.0),
+                    (Vector3::new(0.0, -1.0, sy), 0.0),
+                    (Vector3::new(0.0, 1.0, sy), 0.0),
+                ]
+            },
+            ProjectionKind::Orthographic => {
+                let (hx, hy) = (self.ortho_half_width * kx, self.ortho_half_width * ky);
+                [
+                    near, far,
+                    (Vector3::new(-1.0, 0.0, 0.0), hx),
+                    (Vector3::new(1.0, 0.0, 0.0), hx),
+                    (Vector3::new(0.0, -1.0, 0.0), hy),
+                    (Vector3::new(0.0, 1.0, 0.0), hy),
+                ]
+            },
+        }
+    }
+
+    // Position in the view of `p`, which must be in front of the near plane
+    pub fn to_viewport(&self, p: &Point3<f64>, view: [f64; 4]) -> [f64; 2] {
+        let scale = self.pixels_per_unit(p.z, view);
+        let cx = view[0] + view[2] * 0.5;
+        let cy = view[1] + view[3] * 0.5;
+        [p.x * scale + cx, p.y * scale + cy]
+    }
+
+    // The ray (origin and direction, relative to the camera) of points
+    // which project to `pos` in the view
+    pub fn ray(&self, pos: [f64; 2], view: [f64; 4]) -> (Point3<f64>, Vector3<f64>) {
+        let cx = view[0] + view[2] * 0.5;
+        let cy = view[1] + view[3] * 0.5;
+        let half = 0.5 * util::f64_min(view[2], view[3]);
+        let (u, v) = ((pos[0] - cx) / half, (pos[1] - cy) / half);
+        match self.kind {
+            ProjectionKind::Perspective => {
+                let t = self.half_fov_tan();
+                (Point3::new(0.0, 0.0, 0.0), Vector3::new(u * t, v * t, 1.0))
+            },
+            ProjectionKind::Orthographic => {
+                let w = self.ortho_half_width;
+                (Point3::new(u * w, v * w, 0.0), Vector3::new(0.0, 0.0, 1.0))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use na::Point3;
+
+    use consts::*;
+
+    use super::*;
+
+    const VIEW: [f64; 4] = [0.0, 0.0, 400.0, 300.0];
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    fn at(a: &Point3<f64>, b: &Point3<f64>, t: f64) -> Point3<f64> {
+        *a + (*b - *a) * t
+    }
+
+    #[test]
+    fn segment_through_near_plane_starts_on_it() {
+        let projection = Projection::new();
+        let (a, b) = (Point3::new(3.0, -2.0, -5.0), Point3::new(3.0, 2.0, 10.0));
+        let (t0, t1) = projection.clip_segment(&a, &b, VIEW).unwrap();
+        assert!(close(at(&a, &b, t0).z, NEAR_PLANE_Z));
+        assert_eq!(t1, 1.0);
+    }
+
+    #[test]
+    fn segment_behind_camera_is_hidden() {
+        let projection = Projection::new();
+        let (a, b) = (Point3::new(0.0, 0.0, -5.0), Point3::new(10.0, 0.0, -1.0));
+        assert!(projection.clip_segment(&a, &b, VIEW).is_none());
+    }
+
+    #[test]
+    fn segment_in_view_is_whole() {
+        let projection = Projection::new();
+        let (a, b) = (Point3::new(-5.0, 0.0, 50.0), Point3::new(5.0, 5.0, 60.0));
+        assert_eq!(projection.clip_segment(&a, &b, VIEW), Some((0.0, 1.0)));
+    }
+
+    #[test]
+    fn segment_through_sides_ends_on_edge() {
+        let projection = Projection::new();
+        let (a, b) = (Point3::new(0.0, 0.0, 50.0), Point3::new(200.0, 0.0, 50.0));
+        let (t0, t1) = projection.clip_segment(&a, &b, VIEW).unwrap();
+        assert_eq!(t0, 0.0);
+        assert!(close(projection.to_viewport(&at(&a, &b, t1), VIEW)[0], VIEW[0] + VIEW[2]));
+
+        let (a, b) = (Point3::new(0.0, 0.0, 50.0), Point3::new(0.0, 200.0, 80.0));
+        let (_, t1) = projection.clip_segment(&a, &b, VIEW).unwrap();
+        assert!(close(projection.to_viewport(&at(&a, &b, t1), VIEW)[1], VIEW[1] + VIEW[3]));
+    }
+
+    #[test]
+    fn orthographic_segment_through_side() {
+        let mut projection = Projection::new();
+        projection.kind = ProjectionKind::Orthographic;
+        let (a, b) = (Point3::new(-500.0, 10.0, 30.0), Point3::new(0.0, 10.0, 30.0));
+        let (t0, t1) = projection.clip_segment(&a, &b, VIEW).unwrap();
+        assert!(close(projection.to_viewport(&at(&a, &b, t0), VIEW)[0], VIEW[0]));
+        assert_eq!(t1, 1.0);
+    }
+
+    #[test]
+    fn polygon_through_near_plane_loses_its_tip() {
+        let projection = Projection::new();
+        let triangle = [Point3::new(0.0, 0.0, -4.0), Point3::new(5.0, 0.0, 6.0), Point3::new(-5.0, 0.0, 6.0)];
+        let pts = projection.clip_polygon(&triangle).unwrap();
+        assert_eq!(pts.len(), 4);
+        assert!(pts.iter().all(|p| p.z >= NEAR_PLANE_Z - 1e-9));
+    }
+
+    #[test]
+    fn polygon_behind_camera_is_hidden() {
+        let projection = Projection::new();
+        let triangle = [Point3::new(0.0, 0.0, -4.0), Point3::new(5.0, 0.0, -6.0), Point3::new(-5.0, 0.0, -6.0)];
+        assert!(projection.clip_polygon(&triangle).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use image::{Rgba, RgbaImage};
+
+use draw_list::{DrawList, Primitive};
+
+use util;
+use consts::*;
+
+// Software rendering of draw lists into an image, for recording frames
+// without a window. Shapes are drawn at RASTER_SUPERSAMPLE times the size
+// of the image and averaged down, which smooths their edges.
+pub struct Raster {
+    width: u32,
+    height: u32,
+    // Supersampled pixels, as RGB in [0.0, 1.0]
+    pixels: Vec<[f32; 3]>,
+}
+
+impl Raster {
+    // A white image `width` by `height` pixels
+    pub fn new(width: u32, height: u32) -> Raster {
+        let n = (width * height) as usize * RASTER_SUPERSAMPLE * RASTER_SUPERSAMPLE;
+        Raster {
+            width: width,
+            height: height,
+            pixels: vec![[1.0; 3]; n],
+        }
+    }
+
+    // Draw the primitives of `list` in order, in the coordinates of the
+    // view [0, 0, width, height]
+    pub fn draw(&mut self, list: &DrawList) {
+        for &(_, ref primitive) in list.iter() {
+            match *primitive {
+                Primitive::Arrow { line, head, radius, color } => {
+                    let (a, b) = ([line[0], line[1]], [line[2], line[3]]);
+                    self.line(a, b, radius, color);
+                    // As drawn by piston: two strokes back from the tip,
+                    // at 45 degrees to the line
+                    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+                    let len = (dx * dx + dy * dy).sqrt();
+                    if head > 0.0 && len > 1e-9 {
+                        let (ux, uy) = (dx / len * head, dy / len * head);
+                        self.line(b, [b[0] - ux - uy, b[1] - uy + ux], radius, color);
+                        self.line(b, [b[0] - ux + uy, b[1] - uy - ux], radius, color);
+                    }
+                },
+                Primitive::Polygon { ref points, color } => self.fill_convex(points, color),
+                Primitive::Disc { center, radius, color, border } => {
+                    self.fill_disc(center, radius + 0.5, border);
+                    self.fill_disc(center, radius - 0.5, color);
+                },
+            }
+        }
+    }
+
+    // The image, averaged down to its final size
+    pub fn finish(&self) -> RgbaImage {
+        let ss = RASTER_SUPERSAMPLE;
+        let w = self.width as usize * ss;
+        let mut image = RgbaImage::new(self.width, self.height);
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                let mut sum = [0.0; 3];
+                for sy in 0..ss {
+                    for sx in 0..ss {
+                        let p = self.pixels[(y * ss + sy) * w + x * ss + sx];
+                        for c in 0..3 {
+                            sum[c] += p[c];
+                        }
+                    }
+                }
+                let n = (ss * ss) as f32;
+                let to_u8 = |v: f32| (util::f32_min(util::f32_max(v / n, 0.0), 1.0) * 255.0).round() as u8;
+                image.put_pixel(x as u32, y as u32, Rgba([to_u8(sum[0]), to_u8(sum[1]), to_u8(sum[2]), 255]));
+            }
+        }
+        image
+    }
+
+    fn blend(&mut self, x: usize, y: usize, color: [f32; 4]) {
+        let w = self.width as usize * RASTER_SUPERSAMPLE;
+        let p = &mut self.pixels[y * w + x];
+        for c in 0..3 {
+            p[c] += (color[c] - p[c]) * color[3];
+        }
+    }
+
+    // The range of supersampled pixels (clamped to the image) whose centers
+    // may lie within [lo, hi] along an axis of `size` pixels
+    fn span(lo: f64, hi: f64, size: u32) -> (usize, usize) {
+        let ss = RASTER_SUPERSAMPLE as f64;
+        let max = (size as usize * RASTER_SUPERSAMPLE) as f64;
+        let from = util::f64_min(util::f64_max((lo * ss).floor(), 0.0), max);
+        let to = util::f64_min(util::f64_max((hi * ss).ceil(), 0.0), max);
+        (from as usize, to as usize)
+    }
+
+    // Fill the convex polygon `points`, whichever way it winds
+    fn fill_convex(&mut self, points: &[[f64; 2]], color: [f32; 4]) {
+        if points.len() < 3 {
+            return;
+        }
+        let (mut min, mut max) = ([::std::f64::INFINITY; 2], [::std::f64::NEG_INFINITY; 2]);
+        for p in points {
+            for i in 0..2 {
+                min[i] = util::f64_min(min[i], p[i]);
+                max[i] = util::f64_max(max[i], p[i]);
+            }
+        }
+        let (x0, x1) = Raster::span(min[0], max[0], self.width);
+        let (y0, y1) = Raster::span(min[1], max[1], self.height);
+        let ss = RASTER_SUPERSAMPLE as f64;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = ((x as f64 + 0.5) / ss, (y as f64 + 0.5) / ss);
+                // Inside if on the same side of every edge
+                let (mut pos, mut neg) = (false, false);
+                for i in 0..points.len() {
+                    let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                    let cross = (b[0] - a[0]) * (py - a[1]) - (b[1] - a[1]) * (px - a[0]);
+                    pos |= cross > 0.0;
+                    neg |= cross < 0.0;
+                }
+                if !(pos && neg) {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    // A line `radius` pixels thick on either side, with square ends
+    fn line(&mut self, a: [f64; 2], b: [f64; 2], radius: f64, color: [f32; 4]) {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let len = util::f64_max((dx * dx + dy * dy).sqrt(), 1e-9);
+        // Along and across the line, `radius` long
+        let (ux, uy) = (dx / len * radius, dy / len * radius);
+        let (nx, ny) = (-uy, ux);
+        self.fill_convex(&[
+            [a[0] - ux + nx, a[1] - uy + ny],
+            [b[0] + ux + nx, b[1] + uy + ny],
+            [b[0] + ux - nx, b[1] + uy - ny],
+            [a[0] - ux - nx, a[1] - uy - ny],
+        ], color);
+    }
+
+    fn fill_disc(&mut self, center: [f64; 2], radius: f64, color: [f32; 4]) {
+        if radius <= 0.0 {
+            return;
+        }
+        let (x0, x1) = Raster::span(center[0] - radius, center[0] + radius, self.width);
+        let (y0, y1) = Raster::span(center[1] - radius, center[1] + radius, self.height);
+        let ss = RASTER_SUPERSAMPLE as f64;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (dx, dy) = ((x as f64 + 0.5) / ss - center[0], (y as f64 + 0.5) / ss - center[1]);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+}
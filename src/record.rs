@@ -0,0 +1,307 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use gif::{self, SetParameter};
+
+use arrow::ArrowStyle;
+use camera::{self, OrbitControl};
+use particles::Particles;
+use raster::Raster;
+use scene::Scenes;
+use sweep::Parameter;
+
+use util;
+use consts::*;
+
+// A description of an animation to record: the scene, what changes over
+// time, and where the frames go
+pub struct Timeline {
+    pub scene: String,
+    pub size: (u32, u32),
+    pub fps: f64,
+    // Seconds
+    pub duration: f64,
+    // Directory to which the frames are written
+    pub output: PathBuf,
+    // Also write the frames as `animation.gif` in `output`
+    pub gif: bool,
+    pub arrow_style: Option<ArrowStyle>,
+    // Move the camera along the scene's keyframed camera path, the
+    // timeline's time being the path's
+    pub camera_path: bool,
+    // Number of flow particles and their speed (see `Particles`), if shown
+    pub particles: Option<(usize, f64)>,
+    pub tracks: Vec<Track>,
+}
+
+// Something which changes over the seconds [start, end] of a timeline.
+// Values are interpolated linearly between the given ends.
+pub struct Track {
+    pub kind: TrackKind,
+    pub start: f64,
+    pub end: f64,
+}
+
+pub enum TrackKind {
+    // Orbit the camera about the center of the grid, by the given angles
+    // (degrees) from where it started
+    Orbit { yaw: (f64, f64), pitch: (f64, f64) },
+    // Sweep a parameter of the charges
+    Parameter { parameter: Parameter, values: (f64, f64) },
+}
+
+impl Track {
+    // How far through the track time `t` is, in [0.0, 1.0]
+    fn progress(&self, t: f64) -> f64 {
+        if self.end <= self.start {
+            return if t >= self.start { 1.0 } else { 0.0 };
+        }
+        util::f64_min(util::f64_max((t - self.start) / (self.end - self.start), 0.0), 1.0)
+    }
+}
+
+fn lerp(range: (f64, f64), s: f64) -> f64 {
+    range.0 + (range.1 - range.0) * s
+}
+
+impl Timeline {
+    // Read a timeline file, in the format of scene files (see
+    // `Scene::load`). The keys are:
+    //     scene: <name of a built-in or loaded scene>
+    //     size: <width> <height>         of the frames, in pixels
+    //     fps: <frames per second>
+    //     duration: <seconds>
+    //     output: <directory>            relative to the working directory
+    //     gif: on | off
+    //     arrows: lines | glyphs
+    //     camera_path: on | off          follow the scene's keyframes
+    //     particles: <count> <speed>
+    //     orbit: <yaw from> <yaw to> <pitch from> <pitch to> [<start> <end>]
+    //     charge: <index> <from> <to> [<start> <end>]
+    //     position: <index> <axis: 0, 1 or 2> <from> <to> [<start> <end>]
+    //     separation: <from> <to> [<start> <end>]
+    //                                    (see sweep::Parameter)
+    // Tracks without a start and end last the whole timeline. The angles of
+    // overlapping orbit tracks add up.
+    pub fn load(path: &Path) -> Result<Timeline, String> {
+        let mut text = String::new();
+        try!(File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| e.to_string()));
+
+        let mut timeline = Timeline {
+            scene: DEFAULT_SCENE.to_string(),
+            size: (RECORD_WIDTH, RECORD_HEIGHT),
+            fps: RECORD_FPS,
+            duration: 0.0,
+            output: PathBuf::from("frames"),
+            gif: false,
+            arrow_style: None,
+            camera_path: false,
+            particles: None,
+            tracks: vec![],
+        };
+        // Tracks whose span is not given, to be stretched over the whole
+        // timeline once its duration is known
+        let mut whole = vec![];
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, rest) = match line.find(':') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => (line, ""),
+            };
+            let err = |msg: &str| format!("line {}: {}", n + 1, msg);
+            let nums = match rest.split_whitespace().map(|s| s.parse::<f64>()).collect::<Result<Vec<f64>, _>>() {
+                Ok(nums) => nums,
+                Err(_) if ["scene", "output", "gif", "arrows", "camera_path"].contains(&key) => vec![],
+                Err(_) => return Err(err(&format!("expected numbers after `{}`", key))),
+            };
+            let numbers = |counts: &[usize]| -> Result<Vec<f64>, String> {
+                if counts.contains(&nums.len()) {
+                    Ok(nums.clone())
+                } else {
+                    Err(err(&format!("expected {:?} numbers after `{}`", counts, key)))
+                }
+            };
+            // The span of a track, if given after its `count` values
+            let span = |v: &[f64], count: usize| if v.len() > count { Some((v[count], v[count + 1])) } else { None };
+            let mut track = None;
+            match key {
+                "scene" => timeline.scene = rest.to_string(),
+                "size" => {
+                    let v = try!(numbers(&[2]));
+                    timeline.size = (v[0] as u32, v[1] as u32);
+                },
+                "fps" => timeline.fps = try!(numbers(&[1]))[0],
+                "duration" => timeline.duration = try!(numbers(&[1]))[0],
+                "output" => timeline.output = PathBuf::from(rest),
+                "gif" => timeline.gif = rest == "on",
+                "camera_path" => timeline.camera_path = rest == "on",
+                "arrows" => timeline.arrow_style = match rest {
+                    "lines" => Some(ArrowStyle::Lines),
+                    "glyphs" => Some(ArrowStyle::Glyphs),
+                    _ => return Err(err("expected `lines` or `glyphs`")),
+                },
+                "particles" => {
+                    let v = try!(numbers(&[2]));
+                    timeline.particles = Some((v[0] as usize, v[1]));
+                },
+                "orbit" => {
+                    let v = try!(numbers(&[4, 6]));
+                    track = Some((TrackKind::Orbit { yaw: (v[0], v[1]), pitch: (v[2], v[3]) }, span(&v, 4)));
+                },
+                "charge" => {
+                    let v = try!(numbers(&[3, 5]));
+                    let parameter = Parameter::Charge(v[0] as usize);
+                    track = Some((TrackKind::Parameter { parameter: parameter, values: (v[1], v[2]) }, span(&v, 3)));
+                },
+                "position" => {
+                    let v = try!(numbers(&[4, 6]));
+                    if v[1] != 0.0 && v[1] != 1.0 && v[1] != 2.0 {
+                        return Err(err("the axis must be 0, 1 or 2"));
+                    }
+                    let parameter = Parameter::Position(v[0] as usize, v[1] as usize);
+                    track = Some((TrackKind::Parameter { parameter: parameter, values: (v[2], v[3]) }, span(&v, 4)));
+                },
+                "separation" => {
+                    let v = try!(numbers(&[2, 4]));
+                    let parameter = Parameter::Separation;
+                    track = Some((TrackKind::Parameter { parameter: parameter, values: (v[0], v[1]) }, span(&v, 2)));
+                },
+                _ => return Err(err(&format!("unknown key `{}`", key))),
+            }
+            match track {
+                Some((kind, Some((start, end)))) => timeline.tracks.push(Track { kind: kind, start: start, end: end }),
+                Some((kind, None)) => whole.push(kind),
+                None => {},
+            }
+        }
+
+        if timeline.duration <= 0.0 || timeline.fps <= 0.0 {
+            return Err("`duration` and `fps` must be positive".to_string());
+        }
+        if timeline.size.0 == 0 || timeline.size.1 == 0 || timeline.size.0 > 0xffff || timeline.size.1 > 0xffff {
+            return Err("invalid frame `size`".to_string());
+        }
+        let duration = timeline.duration;
+        timeline.tracks.extend(whole.into_iter().map(|kind| Track { kind: kind, start: 0.0, end: duration }));
+        Ok(timeline)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        (self.duration * self.fps).round() as usize
+    }
+}
+
+// Render every frame of `timeline` without a window, writing them as
+// numbered PNGs (and a GIF if asked for). Returns the number of frames.
+pub fn record(timeline: &Timeline, scenes: &mut Scenes) -> Result<usize, String> {
+    let index = try!(scenes.index_of(&timeline.scene)
+        .ok_or(format!("no scene named `{}`", timeline.scene)));
+    try!(fs::create_dir_all(&timeline.output).map_err(|e| e.to_string()));
+    let (w, h) = timeline.size;
+    let view = [0.0, 0.0, w as f64, h as f64];
+    let dt = 1.0 / timeline.fps;
+
+    let scene = scenes.get_mut(index);
+    let field = &mut *scene.view;
+    let camera_path = &scene.camera_path;
+    if timeline.camera_path && camera_path.len() == 0 {
+        return Err(format!("`{}` has no camera keyframes", timeline.scene));
+    }
+    if let Some(style) = timeline.arrow_style {
+        field.set_arrow_style(style);
+    }
+    let orbit = OrbitControl::new(camera::grid_center(field));
+    let mut particles = timeline.particles.map(|(count, speed)| {
+        let mut particles = Particles::new();
+        particles.count = count;
+        particles.speed = speed;
+        // Let the particles spread out and grow their trails before the
+        // first frame
+        for _ in 0..(RECORD_PARTICLE_WARMUP * timeline.fps) as usize {
+            particles.advance(&*field, dt);
+        }
+        particles
+    });
+
+    let mut gif_encoder = if timeline.gif {
+        let file = try!(File::create(timeline.output.join("animation.gif")).map_err(|e| e.to_string()));
+        let mut encoder = try!(gif::Encoder::new(file, w as u16, h as u16, &[]).map_err(|e| e.to_string()));
+        try!(encoder.set(gif::Repeat::Infinite).map_err(|e| e.to_string()));
+        Some(encoder)
+    } else {
+        None
+    };
+
+    // Orbit angles so far of each track, so that each frame orbits by the
+    // difference
+    let mut angles = vec![(0.0, 0.0); timeline.tracks.len()];
+    let frames = timeline.frame_count();
+    for frame in 0..frames {
+        let t = frame as f64 * dt;
+        let mut charges_changed = false;
+        if timeline.camera_path {
+            if let Some(camera) = camera_path.camera_at(t) {
+                field.set_camera(camera);
+                // Orbit tracks then apply their whole angle about the path
+                for angle in &mut angles {
+                    *angle = (0.0, 0.0);
+                }
+            }
+        }
+        for (track, angle) in timeline.tracks.iter().zip(angles.iter_mut()) {
+            let s = track.progress(t);
+            match track.kind {
+                TrackKind::Orbit { yaw: yaws, pitch: pitches } => {
+                    let (yaw, pitch) = *angle;
+                    let (new_yaw, new_pitch) = (lerp(yaws, s).to_radians(), lerp(pitches, s).to_radians());
+                    orbit.orbit(field, new_pitch - pitch, new_yaw - yaw);
+                    *angle = (new_yaw, new_pitch);
+                },
+                TrackKind::Parameter { parameter, values } => {
+                    let field = try!(field.point_charges_mut()
+                        .ok_or("parameter tracks need a scene of point charges".to_string()));
+                    if !parameter.applies_to(field) {
+                        return Err(format!("`{}` has no {}", timeline.scene, parameter.name()));
+                    }
+                    parameter.set(field, lerp(values, s));
+                    charges_changed = true;
+                },
+            }
+        }
+        if charges_changed {
+            field.rebuild();
+            field.reapply_arrow_transforms();
+        }
+        if let Some(ref mut particles) = particles {
+            particles.advance(field, dt);
+        }
+
+        let mut raster = Raster::new(w, h);
+        raster.draw(&field.draw_list(view));
+        if let Some(ref particles) = particles {
+            raster.draw(&particles.draw_list(field, view));
+        }
+        if let Some(charges) = field.point_charges() {
+            raster.draw(&charges.charges_draw_list(view));
+        }
+        let image = raster.finish();
+
+        let path = timeline.output.join(format!("frame_{:05}.png", frame));
+        try!(image.save(&path).map_err(|e| format!("{}: {}", path.display(), e)));
+        if let Some(ref mut encoder) = gif_encoder {
+            let mut pixels = image.into_raw();
+            let mut gif_frame = gif::Frame::from_rgba(w as u16, h as u16, &mut pixels);
+            gif_frame.delay = (100.0 / timeline.fps).round() as u16;
+            try!(encoder.write_frame(&gif_frame).map_err(|e| e.to_string()));
+        }
+        println!("Frame {}/{}", frame + 1, frames);
+    }
+    Ok(frames)
+}
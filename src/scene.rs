@@ -0,0 +1,398 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use na::{self, Point3, Vector3};
+
+use field::FieldView;
+use point_charge::{PointCharge, PointChargesFieldView};
+use pose::Pose;
+use keyframes::{CameraPath, Keyframe};
+use units::Units;
+
+use consts::*;
+
+// A field which can be chosen in the sidebar, along with the controls
+// shown for it
+pub struct Scene {
+    pub name: String,
+    pub view: Box<FieldView>,
+    pub controls: Vec<Control>,
+    // Keyframes for fly-throughs of the scene
+    pub camera_path: CameraPath,
+    // The file the scene was loaded from, if it is not built in
+    pub path: Option<PathBuf>,
+}
+
+// A widget shown in the sidebar while a scene is selected
+pub enum Control {
+    // Text describing the scene or the controls below
+    Note(String),
+    // Slider setting the value of the charge `index` within `range`. If
+    // `keep_sign` is set, the range is negated when the charge is negative,
+    // so that the slider cannot flip the charge's sign.
+    ChargeSlider { index: usize, label: String, range: (f64, f64), keep_sign: bool },
+    // Button negating every charge of the scene
+    InvertCharges,
+}
+
+impl Scene {
+    // Build the arrows of `view`, rotated by `field_rotation`, and wrap it
+    // up as a scene
+    pub fn new(name: &str, mut view: Box<FieldView>, field_rotation: Pose, controls: Vec<Control>) -> Scene {
+        view.transform_arrows(&field_rotation);
+        view.populate_field();
+        view.reapply_arrow_transforms();
+        Scene {
+            name: name.to_string(),
+            view: view,
+            controls: controls,
+            camera_path: CameraPath::new(),
+            path: None,
+        }
+    }
+
+    // The name, made suitable for file names
+    pub fn slug(&self) -> String {
+        self.name.to_lowercase().replace(' ', "-")
+    }
+
+    // Read a scene file. Each line is a `key: values` pair; blank lines and
+    // lines starting with `#` are ignored. The keys are:
+    //     name: <name shown on the scene's button>
+    //     camera: <x> <y> <z>             initial offset of the camera
+    //     greatest_field: <f>             field strength of the longest arrow
+    //     potential_range: <least> <greatest>  potentials of lightest and darkest arrows
+    //     bounds: <x0> <x1> <y0> <y1> <z0> <z1>  region filled with arrows
+    //     spacing: <s>                    distance between arrows
+    //     adaptive: <budget> | off        adaptive grid with an arrow budget
+    //     field_rotation: <x> <y> <z>     initial rotation of the field, in degrees
+    //     charge: <q> <x> <y> <z> [mass]
+    //     note: <text>
+    //     slider: <charge index> <min> <max> <label>
+    //     sign_slider: <charge index> <min> <max> <label>  (see Control::ChargeSlider)
+    //     invert_button
+    //     keyframe: <time> <camera rotation as qw qi qj qk> <camera translation>
+    //                                     (see Keyframe::parse)
+    //     units: legacy | si <meters per unit of length> <coulombs per unit of charge>
+    //                                     (see Units; legacy if not given)
+    pub fn load(path: &Path) -> Result<Scene, String> {
+        let mut text = String::new();
+        try!(File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| e.to_string()));
+
+        let mut name = None;
+        let mut camera = Vector3::new(0.0, 0.0, 75.0);
+        let mut greatest_field = None;
+        let mut pot_range = None;
+        let mut bounds = None;
+        let mut spacing = None;
+        let mut budget = None;
+        let mut rotation = Pose::identity();
+        let mut charges = vec![];
+        let mut controls = vec![];
+        let mut keyframes = vec![];
+        let mut units = Units::legacy();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, rest) = match line.find(':') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => (line, ""),
+            };
+            let err = |msg: &str| format!("line {}: {}", n + 1, msg);
+            let numbers = |count: usize| -> Result<Vec<f64>, String> {
+                let nums: Result<Vec<f64>, _> = rest.split_whitespace().take(count).map(|s| s.parse::<f64>()).collect();
+                match nums {
+                    Ok(ref nums) if nums.len() == count => Ok(nums.clone()),
+                    _ => Err(err(&format!("expected {} numbers after `{}`", count, key))),
+                }
+            };
+            match key {
+                "name" => name = Some(rest.to_string()),
+                "camera" => {
+                    let v = try!(numbers(3));
+                    camera = Vector3::new(v[0], v[1], v[2]);
+                },
+                "greatest_field" => greatest_field = Some(try!(numbers(1))[0]),
+                "potential_range" => {
+                    let v = try!(numbers(2));
+                    pot_range = Some((v[0], v[1]));
+                },
+                "bounds" => {
+                    let v = try!(numbers(6));
+                    bounds = Some([(v[0], v[1]), (v[2], v[3]), (v[4], v[5])]);
+                },
+                "spacing" => spacing = Some(try!(numbers(1))[0]),
+                "adaptive" => {
+                    budget = if rest == "off" { None } else { Some(try!(numbers(1))[0] as usize) };
+                },
+                "field_rotation" => {
+                    let v = try!(numbers(3));
+                    rotation = Pose::euler(v[0].to_radians(), v[1].to_radians(), v[2].to_radians());
+                },
+                "charge" => {
+                    let v = try!(numbers(4));
+                    let mut chg = PointCharge::new(v[0], Point3::new(v[1], v[2], v[3]));
+                    if let Some(mass) = rest.split_whitespace().nth(4) {
+                        chg.mass = try!(mass.parse().map_err(|_| err("invalid mass")));
+                    }
+                    charges.push(chg);
+                },
+                "note" => controls.push(Control::Note(rest.to_string())),
+                "slider" | "sign_slider" => {
+                    let v = try!(numbers(3));
+                    let index = v[0] as usize;
+                    if index >= charges.len() {
+                        return Err(err("slider refers to a charge not yet defined"));
+                    }
+                    let label = rest.split_whitespace().skip(3).collect::<Vec<_>>().join(" ");
+                    controls.push(Control::ChargeSlider {
+                        index: index,
+                        label: format!("{} ", label),
+                        range: (v[1], v[2]),
+                        keep_sign: key == "sign_slider",
+                    });
+                },
+                "invert_button" => controls.push(Control::InvertCharges),
+                "keyframe" => keyframes.push(try!(Keyframe::parse(rest).map_err(|e| err(&e)))),
+                "units" => {
+                    units = if rest == "legacy" {
+                        Units::legacy()
+                    } else if rest.starts_with("si") {
+                        let v: Vec<f64> = try!(rest[2..].split_whitespace()
+                            .map(|s| s.parse::<f64>())
+                            .collect::<Result<_, _>>()
+                            .map_err(|_| err("expected numbers after `units: si`")));
+                        if v.len() != 2 || v[0] <= 0.0 || v[1] <= 0.0 {
+                            return Err(err("expected 2 positive numbers after `units: si`"));
+                        }
+                        Units::si(v[0], v[1])
+                    } else {
+                        return Err(err("expected `legacy` or `si`"));
+                    };
+                },
+                _ => return Err(err(&format!("unknown key `{}`", key))),
+            }
+        }
+
+        let name = try!(name.ok_or("missing `name`".to_string()));
+        let greatest_field = try!(greatest_field.ok_or("missing `greatest_field`".to_string()));
+        let (least_pot, greatest_pot) = try!(pot_range.ok_or("missing `potential_range`".to_string()));
+        let mut view = PointChargesFieldView::new(camera, greatest_field, least_pot, greatest_pot, charges);
+        view.units = units;
+        if let Some(bounds) = bounds {
+            view.set_bounds(bounds);
+        }
+        if let Some(spacing) = spacing {
+            view.set_grid_spacing(spacing);
+        }
+        if let Some(budget) = budget {
+            view.adaptive_grid = true;
+            view.arrow_budget = budget;
+        }
+        let mut scene = Scene::new(&name, Box::new(view), rotation, controls);
+        for keyframe in keyframes {
+            scene.camera_path.insert(keyframe);
+        }
+        scene.path = Some(path.to_path_buf());
+        Ok(scene)
+    }
+}
+
+// The scenes which can be chosen, in the order of their buttons
+pub struct Scenes {
+    scenes: Vec<Scene>,
+    // Where scenes were loaded from, and where the camera paths of built-in
+    // scenes are saved
+    dir: Option<PathBuf>,
+}
+
+impl Scenes {
+    pub fn builtin() -> Scenes {
+        let slider = |index: usize, label: &str, range: (f64, f64), keep_sign: bool| Control::ChargeSlider {
+            index: index,
+            label: label.to_string(),
+            range: range,
+            keep_sign: keep_sign,
+        };
+        let intro = || Control::Note("Set magnitudes of charges:".to_string());
+        let tfghry = || Control::Note("Use TFGH,RY to move the selected charge (initially the right-side one) around".to_string());
+        let two_charges_same = vec![
+            intro(),
+            slider(1, "Left charge: ", (0.1, 10.0), true),
+            slider(0, "Right charge: ", (0.1, 10.0), true),
+            Control::InvertCharges,
+            tfghry(),
+        ];
+        let two_charges_np = vec![
+            intro(),
+            slider(1, "Left charge: ", (0.0, -10.0), false),
+            slider(0, "Right charge: ", (0.0, 10.0), false),
+            tfghry(),
+        ];
+
+        Scenes {
+            scenes: vec![
+                Scene::new("One charge", Box::new(PointChargesFieldView::new(
+                    na::Vector3::new(-GRID_S_2, -GRID_S_2, 75.0),
+                    474.0, // greatest field
+                    931.0, // pot corresponding to lightest color
+                    6158.0, // pot corresponding to darkest color
+                    vec![PointCharge::new(8.0, na::Point3::new(GRID_S_2, GRID_S_2, GRID_S_2))]
+                )), Pose::euler(0.0, ::std::f64::consts::PI * 0.08, 0.0), vec![]),
+                Scene::new("Two identical charges", Box::new(PointChargesFieldView::new(
+                    na::Vector3::new(0.0, -GRID_S_2, 75.0),
+                    602.4, // greatest field
+                    2343.0, // pot corresponding to lightest color
+                    9161.0, // pot corresponding to darkest color
+                    vec![
+                        PointCharge::new(8.0, na::Point3::new(5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+                        PointCharge::new(8.0, na::Point3::new(-5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+                    ]
+                )), Pose::identity(), two_charges_same),
+                Scene::new("Two opposite charges", Box::new(PointChargesFieldView::new(
+                    na::Vector3::new(0.0, -GRID_S_2, 75.0),
+                    602.4, // greatest field
+                    -6495.8, // pot corresponding to lightest color
+                    6495.8, // pot corresponding to darkest color
+                    vec![
+                        PointCharge::new(8.0, na::Point3::new(5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+                        PointCharge::new(-8.0, na::Point3::new(-5.0 * GRID_S_2, GRID_S_2, GRID_S_2)),
+                    ]
+                )), Pose::identity(), two_charges_np),
+                Scene::new("Capacitor", Box::new(PointChargesFieldView::new_capacitor(
+                    na::Vector3::new(0.0, 5.0, 75.0),
+                    1454.5,
+                    49524.8
+                )), Pose::identity(), vec![]),
+            ],
+            dir: None,
+        }
+    }
+
+    // Add every `.scene` file in `dir`, in order of file name, then apply
+    // every `.keyframes` file (see `save_camera_path`). Files which cannot
+    // be read are skipped, and why is returned for display.
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<String> {
+        self.dir = Some(dir.to_path_buf());
+        let mut errors = vec![];
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(_) => return errors,
+        };
+        paths.sort();
+        let has_extension = |p: &PathBuf, extension: &str| p.extension().map_or(false, |ext| ext == extension);
+        for path in paths.iter().filter(|p| has_extension(p, "scene")) {
+            if self.scenes.len() >= MAX_SCENES {
+                errors.push(format!("Not loading {}: at most {} scenes are supported", path.display(), MAX_SCENES));
+                continue;
+            }
+            match Scene::load(path) {
+                Ok(scene) => self.scenes.push(scene),
+                Err(e) => errors.push(format!("Could not load scene {}: {}", path.display(), e)),
+            }
+        }
+        for path in paths.iter().filter(|p| has_extension(p, "keyframes")) {
+            if let Err(e) = self.load_keyframes(path) {
+                errors.push(format!("Could not load keyframes {}: {}", path.display(), e));
+            }
+        }
+        errors
+    }
+
+    // Read a file of `keyframe:` lines (as in scene files) following a
+    // `scene: <name>` line, and make them the camera path of that scene
+    fn load_keyframes(&mut self, path: &Path) -> Result<(), String> {
+        let mut text = String::new();
+        try!(File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| e.to_string()));
+        let mut scene = None;
+        let mut camera_path = CameraPath::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, rest) = match line.find(':') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => (line, ""),
+            };
+            match key {
+                "scene" => scene = Some(try!(self.index_of(rest)
+                    .ok_or(format!("line {}: no scene named `{}`", n + 1, rest)))),
+                "keyframe" => camera_path.insert(try!(Keyframe::parse(rest)
+                    .map_err(|e| format!("line {}: {}", n + 1, e)))),
+                _ => return Err(format!("line {}: unknown key `{}`", n + 1, key)),
+            }
+        }
+        let scene = try!(scene.ok_or("missing `scene`".to_string()));
+        self.scenes[scene].camera_path = camera_path;
+        Ok(())
+    }
+
+    // Write the camera path of scene `i`, replacing the keyframes in its
+    // scene file or, for a built-in scene, in a `.keyframes` file beside
+    // the scene files. Returns the file written.
+    pub fn save_camera_path(&self, i: usize) -> Result<PathBuf, String> {
+        let scene = &self.scenes[i];
+        let keyframes: Vec<String> = scene.camera_path.keyframes().iter().map(|k| k.to_line()).collect();
+        let (path, mut lines) = match scene.path {
+            Some(ref path) => {
+                let mut text = String::new();
+                try!(File::open(path)
+                    .and_then(|mut f| f.read_to_string(&mut text))
+                    .map_err(|e| e.to_string()));
+                let kept: Vec<String> = text.lines()
+                    .filter(|line| !line.trim().starts_with("keyframe"))
+                    .map(|line| line.to_string())
+                    .collect();
+                (path.clone(), kept)
+            },
+            None => {
+                let dir = try!(self.dir.as_ref().ok_or("no scene directory".to_string()));
+                let path = dir.join(format!("{}.keyframes", scene.slug()));
+                (path, vec!["# Camera path of a built-in scene".to_string(), format!("scene: {}", scene.name)])
+            },
+        };
+        lines.extend(keyframes);
+        try!(File::create(&path)
+            .and_then(|mut f| f.write_all((lines.join("\n") + "\n").as_bytes()))
+            .map_err(|e| e.to_string()));
+        Ok(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.scenes.iter().position(|s| s.name == name)
+    }
+
+    pub fn get(&self, i: usize) -> &Scene {
+        &self.scenes[i]
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> &mut Scene {
+        &mut self.scenes[i]
+    }
+
+    pub fn iter_mut(&mut self) -> ::std::slice::IterMut<Scene> {
+        self.scenes.iter_mut()
+    }
+
+    pub fn view_mut(&mut self, i: usize) -> &mut FieldView {
+        &mut *self.scenes[i].view
+    }
+
+    // The charges of scene `i`, if its field is that of point charges
+    pub fn charges_mut(&mut self, i: usize) -> Option<&mut PointChargesFieldView> {
+        self.scenes[i].view.point_charges_mut()
+    }
+}
@@ -0,0 +1,284 @@
+use std::f64::consts::PI;
+
+use na::{Point3, Vector3, Norm};
+
+use field::FieldView;
+use point_charge::PointChargesFieldView;
+
+use util;
+use consts::*;
+
+// A numeric property of a field of point charges which can be animated
+#[derive(Clone, Copy, PartialEq)]
+pub enum Parameter {
+    // The value of charge `index`
+    Charge(usize),
+    // Coordinate `axis` (0, 1 or 2 for x, y or z) of the location of charge
+    // `index`
+    Position(usize, usize),
+    // The distance between the centroids of the positive and the negative
+    // charges, eg. the plates of a capacitor. Changing it moves each group
+    // rigidly along the line joining the centroids.
+    Separation,
+}
+
+impl Parameter {
+    pub fn name(&self) -> String {
+        match *self {
+            Parameter::Charge(i) => format!("charge {}", i),
+            Parameter::Position(i, axis) => format!("{} of charge {}", ["x", "y", "z"][axis], i),
+            Parameter::Separation => "separation of + and -".to_string(),
+        }
+    }
+
+    // Whether `field` has the parameter
+    pub fn applies_to(&self, field: &PointChargesFieldView) -> bool {
+        match *self {
+            Parameter::Charge(i) | Parameter::Position(i, _) => i < field.charges.len(),
+            Parameter::Separation => separation_axis(field).is_some(),
+        }
+    }
+
+    pub fn get(&self, field: &PointChargesFieldView) -> f64 {
+        match *self {
+            Parameter::Charge(i) => field.charges[i].charge,
+            Parameter::Position(i, axis) => {
+                let loc = field.charges[i].loc;
+                [loc.x, loc.y, loc.z][axis]
+            },
+            Parameter::Separation => separation_axis(field).map_or(0.0, |(_, _, separation)| separation),
+        }
+    }
+
+    // Change the parameter of `field` to `value`, marking the changed
+    // charges for the next rebuild. Like the simulation, this bypasses the
+    // edit history: a sweep changes the charges every frame.
+    pub fn set(&self, field: &mut PointChargesFieldView, value: f64) {
+        match *self {
+            Parameter::Charge(i) => {
+                field.charges[i].charge = value;
+                field.charge_changed(i);
+            },
+            Parameter::Position(i, axis) => {
+                {
+                    let loc = &mut field.charges[i].loc;
+                    match axis {
+                        0 => loc.x = value,
+                        1 => loc.y = value,
+                        _ => loc.z = value,
+                    }
+                }
+                field.charge_changed(i);
+            },
+            Parameter::Separation => {
+                if let Some((center, axis, _)) = separation_axis(field) {
+                    let (pos, neg) = (centroid(field, true), centroid(field, false));
+                    let (pos, neg) = (pos.unwrap_or(center), neg.unwrap_or(center));
+                    let pos_shift = (center + axis * (value * 0.5)) - pos;
+                    let neg_shift = (center - axis * (value * 0.5)) - neg;
+                    for chg in &mut field.charges {
+                        chg.loc = chg.loc + if chg.charge >= 0.0 { pos_shift } else { neg_shift };
+                    }
+                    field.charges_changed();
+                }
+            },
+        }
+    }
+
+    // The parameter following this one for the charge `index`, cycling
+    // through its value, its coordinates and the separation
+    pub fn next(&self, index: usize) -> Parameter {
+        match *self {
+            Parameter::Charge(_) => Parameter::Position(index, 0),
+            Parameter::Position(_, axis) if axis < 2 => Parameter::Position(index, axis + 1),
+            Parameter::Position(..) => Parameter::Separation,
+            Parameter::Separation => Parameter::Charge(index),
+        }
+    }
+
+    // The values which the ends of a sweep of the parameter of `field` may
+    // be set to
+    pub fn limits(&self, field: &PointChargesFieldView) -> (f64, f64) {
+        let bounds = field.bounds();
+        let extent = (0..3).map(|i| bounds[i].1 - bounds[i].0).fold(0.0, util::f64_max);
+        match *self {
+            Parameter::Charge(_) => (-MAX_SWEEP_CHARGE, MAX_SWEEP_CHARGE),
+            Parameter::Position(_, axis) => (bounds[axis].0 - extent * 0.5, bounds[axis].1 + extent * 0.5),
+            Parameter::Separation => (0.0, extent * 2.0),
+        }
+    }
+
+    // A sensible range over which to sweep the parameter of `field`
+    pub fn default_range(&self, field: &PointChargesFieldView) -> (f64, f64) {
+        let value = self.get(field);
+        match *self {
+            Parameter::Charge(_) => (value, -value),
+            Parameter::Position(_, axis) => field.bounds()[axis],
+            Parameter::Separation => (value * 0.5, value * 1.5),
+        }
+    }
+}
+
+// Centroid of the positive (or negative) charges, if there are any
+fn centroid(field: &PointChargesFieldView, positive: bool) -> Option<Point3<f64>> {
+    let locs: Vec<Vector3<f64>> = field.charges.iter()
+        .filter(|chg| (chg.charge >= 0.0) == positive)
+        .map(|chg| chg.loc.to_vector())
+        .collect();
+    if locs.is_empty() {
+        return None;
+    }
+    let sum = locs.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, v| sum + *v);
+    Some(Point3::new(0.0, 0.0, 0.0) + sum / locs.len() as f64)
+}
+
+// The midpoint between the centroids of the positive and negative charges,
+// the unit vector from the negative to the positive one, and the distance
+// between them. None unless there are charges of both signs, apart.
+fn separation_axis(field: &PointChargesFieldView) -> Option<(Point3<f64>, Vector3<f64>, f64)> {
+    match (centroid(field, true), centroid(field, false)) {
+        (Some(pos), Some(neg)) => {
+            let d = pos - neg;
+            let separation = d.norm();
+            if separation < 1e-9 {
+                return None;
+            }
+            Some((neg + d * 0.5, d / separation, separation))
+        },
+        _ => None,
+    }
+}
+
+// How a sweep moves from its start value to its end value over its
+// duration
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    Linear,
+    // Starting and ending slowly (smoothstep)
+    Ease,
+    // Half a cosine wave
+    Sine,
+}
+
+impl Curve {
+    pub fn next(&self) -> Curve {
+        match *self {
+            Curve::Linear => Curve::Ease,
+            Curve::Ease => Curve::Sine,
+            Curve::Sine => Curve::Linear,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Curve::Linear => "linear",
+            Curve::Ease => "ease",
+            Curve::Sine => "sine",
+        }
+    }
+
+    // Progress towards the end value at `s` in [0.0, 1.0] through the sweep
+    pub fn at(&self, s: f64) -> f64 {
+        match *self {
+            Curve::Linear => s,
+            Curve::Ease => s * s * (3.0 - 2.0 * s),
+            Curve::Sine => 0.5 - 0.5 * (PI * s).cos(),
+        }
+    }
+}
+
+// What a sweep does when it reaches its end
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    Loop,
+    // Sweep back to the start value, then forwards again, and so on
+    BackAndForth,
+}
+
+impl Repeat {
+    pub fn next(&self) -> Repeat {
+        match *self {
+            Repeat::Once => Repeat::Loop,
+            Repeat::Loop => Repeat::BackAndForth,
+            Repeat::BackAndForth => Repeat::Once,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Repeat::Once => "once",
+            Repeat::Loop => "loop",
+            Repeat::BackAndForth => "back and forth",
+        }
+    }
+}
+
+// An animation of a parameter from one value to another
+pub struct Sweep {
+    pub parameter: Parameter,
+    pub from: f64,
+    pub to: f64,
+    // Seconds
+    pub duration: f64,
+    pub curve: Curve,
+    pub repeat: Repeat,
+    pub time: f64,
+    pub playing: bool,
+}
+
+impl Sweep {
+    pub fn new(parameter: Parameter, from: f64, to: f64, duration: f64) -> Sweep {
+        Sweep {
+            parameter: parameter,
+            from: from,
+            to: to,
+            duration: duration,
+            curve: Curve::Linear,
+            repeat: Repeat::Once,
+            time: 0.0,
+            playing: false,
+        }
+    }
+
+    // The value of the parameter `t` seconds into the sweep
+    pub fn value_at(&self, t: f64) -> f64 {
+        let mut s = if self.duration > 0.0 { t / self.duration } else { 1.0 };
+        s = match self.repeat {
+            Repeat::Once => util::f64_min(s, 1.0),
+            Repeat::Loop => s % 1.0,
+            Repeat::BackAndForth => {
+                let phase = s % 2.0;
+                if phase > 1.0 { 2.0 - phase } else { phase }
+            },
+        };
+        self.from + (self.to - self.from) * self.curve.at(util::f64_max(s, 0.0))
+    }
+
+    pub fn play(&mut self) {
+        self.time = 0.0;
+        self.playing = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    // Move `dt` seconds through the sweep while it plays, setting the
+    // parameter of `field`. Returns whether the field changed.
+    pub fn advance(&mut self, field: &mut PointChargesFieldView, dt: f64) -> bool {
+        if !self.playing {
+            return false;
+        }
+        if !self.parameter.applies_to(field) {
+            self.playing = false;
+            return false;
+        }
+        self.time += dt;
+        if self.repeat == Repeat::Once && self.time >= self.duration {
+            self.playing = false;
+        }
+        self.parameter.set(field, self.value_at(self.time));
+        true
+    }
+}
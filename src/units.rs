@@ -0,0 +1,132 @@
+// Coulomb's constant, in N m^2 / C^2
+pub const COULOMB_CONSTANT: f64 = 8.9875517923e9;
+// The constant used in place of Coulomb's before there were units, giving
+// the built-in scenes their (arbitrary) numbers
+pub const LEGACY_COULOMB_CONSTANT: f64 = 10000.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    // Lengths and charges in arbitrary units, with Coulomb's constant
+    // LEGACY_COULOMB_CONSTANT
+    Legacy,
+    // Lengths and charges as multiples of meters and coulombs, with the
+    // field in N/C and the potential in V
+    SI,
+}
+
+// What the numbers of a scene mean physically. Locations and charges are
+// stored in the scene's units of length and charge; field strengths,
+// potentials, forces and energies are computed in N/C, V, N and J (or in
+// the arbitrary units of the legacy preset).
+#[derive(Clone, Copy, PartialEq)]
+pub struct Units {
+    pub preset: Preset,
+    // Meters per unit of length in the scene
+    pub length: f64,
+    // Coulombs per unit of charge in the scene
+    pub charge: f64,
+}
+
+// A kind of value shown to the user
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    // In the scene's units, converted when displayed
+    Length,
+    Charge,
+    // Already physical, as computed
+    Field,
+    Potential,
+    Force,
+    Energy,
+    // A force in N times a lever arm in the scene's units of length
+    Torque,
+}
+
+impl Units {
+    pub fn legacy() -> Units {
+        Units {
+            preset: Preset::Legacy,
+            length: 1.0,
+            charge: 1.0,
+        }
+    }
+
+    // Units in which a unit of length is `length` meters and a unit of
+    // charge is `charge` coulombs
+    pub fn si(length: f64, charge: f64) -> Units {
+        Units {
+            preset: Preset::SI,
+            length: length,
+            charge: charge,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self.preset {
+            Preset::Legacy => "legacy (arbitrary units)".to_string(),
+            Preset::SI => format!("SI ({} per unit of length, {} per unit of charge)",
+                                  self.format(1.0, Quantity::Length), self.format(1.0, Quantity::Charge)),
+        }
+    }
+
+    fn coulomb_constant(&self) -> f64 {
+        match self.preset {
+            Preset::Legacy => LEGACY_COULOMB_CONSTANT,
+            Preset::SI => COULOMB_CONSTANT,
+        }
+    }
+
+    // Factors by which q / r^2 and q / r, in the scene's units, are
+    // multiplied to give the field and potential of a point charge
+    pub fn field_factor(&self) -> f64 {
+        self.coulomb_constant() * self.charge / (self.length * self.length)
+    }
+
+    pub fn potential_factor(&self) -> f64 {
+        self.coulomb_constant() * self.charge / self.length
+    }
+
+    // Likewise for q1 q2 / r^2 and q1 q2 / r, giving the force between and
+    // the potential energy of two point charges
+    pub fn force_factor(&self) -> f64 {
+        self.field_factor() * self.charge
+    }
+
+    pub fn energy_factor(&self) -> f64 {
+        self.potential_factor() * self.charge
+    }
+
+    // `value` for display, with its unit. Lengths and charges are given in
+    // the scene's units and converted.
+    pub fn format(&self, value: f64, quantity: Quantity) -> String {
+        if self.preset == Preset::Legacy {
+            return format!("{:.3}", value);
+        }
+        let (value, unit) = match quantity {
+            Quantity::Length => (value * self.length, "m"),
+            Quantity::Charge => (value * self.charge, "C"),
+            Quantity::Field => (value, "N/C"),
+            Quantity::Potential => (value, "V"),
+            Quantity::Force => (value, "N"),
+            Quantity::Energy => (value, "J"),
+            Quantity::Torque => (value * self.length, "N m"),
+        };
+        with_prefix(value, unit)
+    }
+}
+
+// `value` scaled by an SI prefix so that it is between 1 and 1000
+fn with_prefix(value: f64, unit: &str) -> String {
+    const PREFIXES: [(f64, &'static str); 9] = [
+        (1e9, "G"), (1e6, "M"), (1e3, "k"), (1.0, ""), (1e-3, "m"),
+        (1e-6, "\u{b5}"), (1e-9, "n"), (1e-12, "p"), (1e-15, "f"),
+    ];
+    let magnitude = value.abs();
+    if magnitude == 0.0 || !magnitude.is_finite() {
+        return format!("{} {}", value, unit);
+    }
+    let &(scale, prefix) = PREFIXES.iter()
+        .find(|&&(scale, _)| magnitude >= scale)
+        .unwrap_or(&PREFIXES[PREFIXES.len() - 1]);
+    format!("{:.3} {}{}", value / scale, prefix, unit)
+}
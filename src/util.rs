@@ -1,7 +1,3 @@
-use na;
-use na::{Vector3, Rotation3, Point4, Matrix4, ToHomogeneous};
-use num::One;
-
 // floating-point max/min fns. If one argument is NaN, the latter will be returned
 pub fn f32_min(x: f32, y: f32) -> f32 {
     if x < y { x } else { y }
@@ -19,36 +15,32 @@ pub fn f64_max(x: f64, y: f64) -> f64 {
     if x > y { x } else { y }
 }
 
-// common matrix transformations
-pub fn translation_mat4<T: na::BaseNum>(v: Vector3<T>) -> Matrix4<T> {
-    let mut res: Matrix4<T> = One::one();
-    res.m14 = v.x;
-    res.m24 = v.y;
-    res.m34 = v.z;
-    res
-}
+// Small, fast pseudo-random number generator (xorshift64*). Used where
+// reproducible "randomness" is needed, eg. for benchmark charge sets.
+pub struct Rng(u64);
 
-pub fn euler_rot_mat4<T: na::BaseFloat>(x: T, y: T, z: T) -> Matrix4<T> {
-    let rot = Rotation3::new_with_euler_angles(x, y, z);
-    rot.submatrix().to_homogeneous()
-}
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // The state must never be zero
+        Rng(if seed == 0 { 0x9E3779B97F4A7C15 } else { seed })
+    }
 
-// The following should be in nalgebra, which implements
-// Mul<Point4<N>> for Matrix4<N> but not also for &'a Matrix<N>.
-// The definition mirrors nalgebra's definition of the method
-// `mul` in `impl... for Matrix4<N>`.
-#[inline]
-pub fn ref_mat4_mul(mat: &Matrix4<f64>, right: Point4<f64>) -> Point4<f64> {
-    let mut res: Point4<f64> = Point4::new(0.0, 0.0, 0.0, 0.0);
-    for i in 0..4 {
-        for j in 0..4 {
-            unsafe {
-                let val = res.at_fast(i) + mat.at_fast((i, j)) * right.at_fast(j);
-                res.set_fast(i, val);
-            }
-        }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    // Uniformly distributed in [0.0, 1.0)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniformly distributed in [lo, hi)
+    pub fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (hi - lo) * self.next_f64()
     }
-    res
 }
 
 macro_rules! slider {